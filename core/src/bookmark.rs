@@ -0,0 +1,207 @@
+//! Parsing of bookmarks.txt
+//!
+//! Every line is a command, an empty line is a separator and a line starting
+//! with `#` is a group header. Indented `key: value` lines below a command
+//! add optional details to it:
+//!
+//! ```text
+//! # Chat
+//! st -e weechat
+//!     name: WeeChat
+//!     icon: internet-group-chat
+//!     tags: chat, irc
+//!     keywords: freenode
+//!     dir: ~/downloads
+//!
+//! htop
+//!     terminal: yes
+//! ```
+
+use config::expand_tilde;
+use launch::shell_quote;
+
+const KEYS: &[&str] = &["name", "icon", "tags", "keywords", "dir", "terminal"];
+
+pub enum Entry {
+    Bookmark(Bookmark),
+    Separator,
+    Header(String),
+}
+
+#[derive(Default)]
+pub struct Bookmark {
+    pub command:  String,
+    pub name:     Option<String>,
+    pub icon:     Option<String>,
+    pub tags:     Vec<String>,
+    pub keywords: Vec<String>,
+
+    /// Working directory to run the command in
+    pub dir:      Option<String>,
+
+    /// Whether to run the command in a terminal emulator
+    pub terminal: bool,
+}
+
+impl Bookmark {
+    /// The name if there is one, the command otherwise
+    pub fn title(&self) -> &str {
+        self.name.as_ref().unwrap_or(&self.command)
+    }
+
+    /// Text that can be matched against when searching
+    pub fn search_fields(&self) -> Vec<&String> {
+        Some(&self.command).into_iter()
+            .chain(self.name.iter())
+            .chain(self.tags.iter())
+            .chain(self.keywords.iter())
+            .collect()
+    }
+
+    /// The shell command to run, including the change of directory
+    pub fn command_line(&self) -> String {
+        match self.dir {
+            Some(ref dir) => format!("cd {} && {}", shell_quote(&expand_tilde(dir)), self.command),
+            None          => self.command.clone(),
+        }
+    }
+}
+
+/// Parse the lines of bookmarks.txt
+pub fn parse(lines: Vec<String>) -> Vec<Entry> {
+    let mut entries = vec![];
+
+    for line in lines {
+        if line.trim() == "" {
+            entries.push(Entry::Separator);
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('#') {
+            entries.push(Entry::Header(header.trim().to_string()));
+            continue;
+        }
+
+        if line.starts_with(char::is_whitespace) {
+            if let Some(&mut Entry::Bookmark(ref mut bookmark)) = entries.last_mut() {
+                if let Some((key, value)) = parse_attribute(&line) {
+                    set_attribute(bookmark, key, value);
+                    continue;
+                }
+            }
+        }
+
+        entries.push(Entry::Bookmark(Bookmark {
+            command: line.trim().to_string(),
+            ..Default::default()
+        }));
+    }
+
+    entries
+}
+
+/// Serialize entries into lines for bookmarks.txt
+pub fn serialize(entries: &[Entry]) -> Vec<String> {
+    let mut lines = vec![];
+
+    for entry in entries {
+        match *entry {
+            Entry::Separator         => lines.push(String::new()),
+            Entry::Header(ref title) => lines.push(format!("# {}", title)),
+            Entry::Bookmark(ref b)   => {
+                lines.push(b.command.clone());
+
+                let attributes = vec![
+                    ("name",     b.name.clone()),
+                    ("icon",     b.icon.clone()),
+                    ("tags",     join_list(&b.tags)),
+                    ("keywords", join_list(&b.keywords)),
+                    ("dir",      b.dir.clone()),
+                    ("terminal", if b.terminal { Some("yes".to_string()) } else { None }),
+                ];
+
+                for (key, value) in attributes {
+                    if let Some(value) = value {
+                        lines.push(format!("    {}: {}", key, value));
+                    }
+                }
+            },
+        }
+    }
+
+    lines
+}
+
+fn join_list(list: &[String]) -> Option<String> {
+    if list.is_empty() { None } else { Some(list.join(", ")) }
+}
+
+/// Split an indented `key: value` line, if the key is known
+fn parse_attribute(line: &str) -> Option<(&str, &str)> {
+    let colon = line.find(':')?;
+    let key   = line[.. colon].trim();
+    let value = line[colon + 1 ..].trim();
+
+    if KEYS.contains(&key) { Some((key, value)) } else { None }
+}
+
+fn set_attribute(bookmark: &mut Bookmark, key: &str, value: &str) {
+    let list = || value.split(',')
+        .map(str::trim)
+        .filter(|s| *s != "")
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+
+    match key {
+        "name"     => bookmark.name     = Some(value.to_string()),
+        "icon"     => bookmark.icon     = Some(value.to_string()),
+        "tags"     => bookmark.tags     = list(),
+        "keywords" => bookmark.keywords = list(),
+        "dir"      => bookmark.dir      = Some(value.to_string()),
+        "terminal" => bookmark.terminal = value == "yes" || value == "true",
+        _          => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_headers_separators_and_attributes() {
+        let entries = parse(lines("# Chat\nst -e weechat\n    name: WeeChat\n    tags: chat, irc\n\nhtop\n    terminal: yes"));
+
+        assert_eq!(entries.len(), 4);
+        match (&entries[0], &entries[1], &entries[2], &entries[3]) {
+            (&Entry::Header(ref h), &Entry::Bookmark(ref chat), &Entry::Separator, &Entry::Bookmark(ref htop)) => {
+                assert_eq!(h, "Chat");
+                assert_eq!(chat.title(), "WeeChat");
+                assert_eq!(chat.tags, vec!["chat", "irc"]);
+                assert!(!chat.terminal);
+                assert!(htop.terminal);
+            },
+            _ => panic!("unexpected entries"),
+        }
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let text = "# Chat\nst -e weechat\n    name: WeeChat\n    tags: chat, irc\n\nhtop\n    dir: /tmp\n    terminal: yes";
+        assert_eq!(serialize(&parse(lines(text))), lines(text));
+    }
+
+    #[test]
+    fn command_line_changes_directory() {
+        let bookmark = Bookmark {
+            command: "make".to_string(),
+            dir:     Some("/src/it's here".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(bookmark.command_line(), "cd '/src/it'\\''s here' && make");
+    }
+}
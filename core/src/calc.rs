@@ -0,0 +1,362 @@
+//! Expression evaluator for the calculator results
+//!
+//! Supports `+ - * / %`, `^` or `**` for powers, parentheses, decimal, hex
+//! (`0x`), octal (`0o`) and binary (`0b`) numbers, and common functions and
+//! constants like `sqrt(2)` and `pi`.
+
+use std::f64::consts;
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    Pow,
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// Evaluate an expression
+pub fn eval(input: &str) -> Result<f64, String> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser { tokens, pos: 0 };
+
+    let value = parser.expr()?;
+
+    match parser.peek() {
+        None        => Ok(value),
+        Some(token) => Err(format!("unexpected {}", describe(token))),
+    }
+}
+
+/// Evaluate the input if it looks like a calculation rather than a search
+///
+/// Plain decimal numbers are left alone, and the input needs at least one
+/// digit so that typing e.g. `e` or `pi` doesn't bring up the calculator.
+pub fn eval_query(input: &str) -> Option<f64> {
+    let input = input.trim();
+
+    if !input.chars().any(|c| c.is_ascii_digit()) { return None }
+    if input.parse::<f64>().is_ok()               { return None }
+
+    eval(input).ok().and_then(|v| if v.is_finite() { Some(v) } else { None })
+}
+
+/// Format a result, without a fractional part if it's a whole number
+pub fn format(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{}", value)
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars = input.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).map_or(false, char::is_ascii_digit)) {
+            let radix = match (c, chars.get(i + 1)) {
+                ('0', Some(&'x')) | ('0', Some(&'X')) => 16,
+                ('0', Some(&'o')) | ('0', Some(&'O')) =>  8,
+                ('0', Some(&'b')) | ('0', Some(&'B')) =>  2,
+                _                                      => 10,
+            };
+
+            if radix != 10 {
+                let start = i + 2;
+                let mut end = start;
+                while end < chars.len() && (chars[end].is_digit(radix) || chars[end] == '_') {
+                    end += 1;
+                }
+
+                let digits = chars[start .. end].iter().filter(|&&c| c != '_').collect::<String>();
+                let value = u64::from_str_radix(&digits, radix)
+                    .map_err(|_| format!("invalid number: {}", chars[i .. end].iter().collect::<String>()))?;
+
+                tokens.push(Token::Number(value as f64));
+                i = end;
+                continue;
+            }
+
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '_') {
+                i += 1;
+            }
+
+            // Exponent, e.g. 1e-3
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let sign = match chars.get(i + 1) { Some(&'+') | Some(&'-') => 1, _ => 0 };
+                if chars.get(i + 1 + sign).map_or(false, char::is_ascii_digit) {
+                    i += 1 + sign;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+
+            let literal = chars[start .. i].iter().filter(|&&c| c != '_').collect::<String>();
+            let value = literal.parse::<f64>()
+                .map_err(|_| format!("invalid number: {}", literal))?;
+
+            tokens.push(Token::Number(value));
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            tokens.push(Token::Ident(chars[start .. i].iter().collect::<String>().to_lowercase()));
+            continue;
+        }
+
+        let token = match c {
+            '*' if chars.get(i + 1) == Some(&'*') => { i += 1; Token::Pow },
+            '^'                                    => Token::Pow,
+            '+' | '-' | '*' | '/' | '%'            => Token::Op(c),
+            '('                                    => Token::LParen,
+            ')'                                    => Token::RParen,
+            ','                                    => Token::Comma,
+            _                                      => return Err(format!("unexpected character: {}", c)),
+        };
+
+        tokens.push(token);
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match *token {
+        Token::Number(n)     => format!("number {}", n),
+        Token::Ident(ref s)  => format!("name {}", s),
+        Token::Op(c)         => format!("operator {}", c),
+        Token::Pow           => "operator ^".to_string(),
+        Token::LParen        => "(".to_string(),
+        Token::RParen        => ")".to_string(),
+        Token::Comma         => ",".to_string(),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos:    usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(ref token) if *token == expected => Ok(()),
+            Some(ref token)                       => Err(format!("expected {}, found {}", describe(&expected), describe(token))),
+            None                                  => Err(format!("expected {}", describe(&expected))),
+        }
+    }
+
+    /// expr = term (("+" | "-") term)*
+    fn expr(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+
+        loop {
+            match self.peek() {
+                Some(&Token::Op('+')) => { self.next(); value += self.term()?; },
+                Some(&Token::Op('-')) => { self.next(); value -= self.term()?; },
+                _                     => return Ok(value),
+            }
+        }
+    }
+
+    /// term = unary (("*" | "/" | "%") unary)*
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+
+        loop {
+            match self.peek() {
+                Some(&Token::Op('*')) => { self.next(); value *= self.unary()?; },
+                Some(&Token::Op('/')) => { self.next(); value /= self.unary()?; },
+                Some(&Token::Op('%')) => { self.next(); value %= self.unary()?; },
+                _                     => return Ok(value),
+            }
+        }
+    }
+
+    /// unary = ("-" | "+") unary | power
+    fn unary(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some(&Token::Op('-')) => { self.next(); Ok(-self.unary()?) },
+            Some(&Token::Op('+')) => { self.next(); self.unary() },
+            _                     => self.power(),
+        }
+    }
+
+    /// power = atom ("^" unary)?, right-associative so 2^3^2 = 2^9
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.atom()?;
+
+        if self.peek() == Some(&Token::Pow) {
+            self.next();
+            Ok(base.powf(self.unary()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// atom = number | "(" expr ")" | constant | function "(" args ")"
+    fn atom(&mut self) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(n),
+            Some(Token::LParen) => {
+                let value = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(value)
+            },
+            Some(Token::Ident(name)) => {
+                if self.peek() == Some(&Token::LParen) {
+                    self.next();
+                    let args = self.args()?;
+                    call(&name, &args)
+                } else {
+                    constant(&name)
+                }
+            },
+            Some(token) => Err(format!("unexpected {}", describe(&token))),
+            None        => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    /// Comma-separated arguments up to and including the closing parenthesis
+    fn args(&mut self) -> Result<Vec<f64>, String> {
+        let mut args = vec![];
+
+        if self.peek() == Some(&Token::RParen) {
+            self.next();
+            return Ok(args);
+        }
+
+        loop {
+            args.push(self.expr()?);
+
+            match self.next() {
+                Some(Token::Comma)  => continue,
+                Some(Token::RParen) => return Ok(args),
+                Some(token)         => return Err(format!("expected , or ), found {}", describe(&token))),
+                None                => return Err("expected )".to_string()),
+            }
+        }
+    }
+}
+
+fn constant(name: &str) -> Result<f64, String> {
+    match name {
+        "pi"  => Ok(consts::PI),
+        "e"   => Ok(consts::E),
+        "tau" => Ok(2.0 * consts::PI),
+        _     => Err(format!("unknown constant: {}", name)),
+    }
+}
+
+fn call(name: &str, args: &[f64]) -> Result<f64, String> {
+    let f: fn(f64) -> f64 = match (name, args.len()) {
+        ("min", n) if n > 0 => return Ok(args.iter().cloned().fold(args[0], f64::min)),
+        ("max", n) if n > 0 => return Ok(args.iter().cloned().fold(args[0], f64::max)),
+        ("pow", 2)          => return Ok(args[0].powf(args[1])),
+        ("atan2", 2)        => return Ok(args[0].atan2(args[1])),
+        ("log", 2)          => return Ok(args[1].log(args[0])),
+        (_, 1) => match name {
+            "sqrt"  => f64::sqrt,
+            "cbrt"  => f64::cbrt,
+            "abs"   => f64::abs,
+            "sin"   => f64::sin,
+            "cos"   => f64::cos,
+            "tan"   => f64::tan,
+            "asin"  => f64::asin,
+            "acos"  => f64::acos,
+            "atan"  => f64::atan,
+            "sinh"  => f64::sinh,
+            "cosh"  => f64::cosh,
+            "tanh"  => f64::tanh,
+            "exp"   => f64::exp,
+            "ln"    => f64::ln,
+            "log"   => f64::log10,
+            "log2"  => f64::log2,
+            "floor" => f64::floor,
+            "ceil"  => f64::ceil,
+            "round" => f64::round,
+            "trunc" => f64::trunc,
+            _       => return Err(format!("unknown function: {}", name)),
+        },
+        _ => return Err(format!("wrong number of arguments for {}", name)),
+    };
+
+    Ok(f(args[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence_and_powers() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(7.0));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(9.0));
+        assert_eq!(eval("2 ^ 3 ^ 2"), Ok(512.0));
+        assert_eq!(eval("2 ** 10"), Ok(1024.0));
+        assert_eq!(eval("-2 ^ 2"), Ok(-4.0));
+        assert_eq!(eval("7 % 4"), Ok(3.0));
+    }
+
+    #[test]
+    fn number_bases_functions_and_constants() {
+        assert_eq!(eval("0xff + 0b1 + 0o10"), Ok(264.0));
+        assert_eq!(eval("sqrt(16) + max(1, 5, 3)"), Ok(9.0));
+        assert_eq!(eval("log(2, 8)"), Ok(3.0));
+        assert_eq!(eval("floor(pi)"), Ok(3.0));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(eval("1 +").is_err());
+        assert!(eval("foo(1)").is_err());
+        assert!(eval("(1").is_err());
+        assert!(eval("1 1").is_err());
+    }
+
+    #[test]
+    fn queries_that_are_not_calculations() {
+        assert_eq!(eval_query("firefox"), None);
+        assert_eq!(eval_query("pi"), None);
+        assert_eq!(eval_query("42"), None);
+        assert_eq!(eval_query("1/0"), None);
+        assert_eq!(eval_query("6 * 7"), Some(42.0));
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format(42.0), "42");
+        assert_eq!(format(-3.0), "-3");
+        assert_eq!(format(0.5), "0.5");
+    }
+}
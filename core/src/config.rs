@@ -0,0 +1,419 @@
+//! User configuration, read from `config.toml` in the config directory
+//!
+//! Every key is optional; missing keys keep their default value. Unknown keys
+//! are rejected so that typos don't go unnoticed.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io;
+use std::path::Path;
+use toml;
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Shell used to run commands that aren't a plain program and arguments
+    pub shell:    String,
+
+    /// Name of a theme in the `themes` directory next to config.toml, without `.css`
+    pub theme:    Option<String>,
+    pub window:   WindowConfig,
+    pub launch:   LaunchConfig,
+    pub terminal: TerminalConfig,
+    pub limits:   LimitsConfig,
+    pub history:  HistoryConfig,
+    pub files:    FilesConfig,
+    pub scripts:  Vec<ScriptConfig>,
+
+    /// Chords for each action by name, see `keys.rs`
+    pub keys:     BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WindowConfig {
+    /// Distance from the edges of the monitor, in pixels at 96 DPI
+    pub padding:  i32,
+    pub width:    i32,
+    pub height:   i32,
+
+    /// Which monitor to show the window on
+    pub monitor:  Monitor,
+
+    /// Where on the monitor to place the window
+    pub anchor:   Anchor,
+
+    /// Moves the window from its anchored position, in pixels at 96 DPI
+    pub offset_x: i32,
+    pub offset_y: i32,
+}
+
+/// A monitor by its index, as numbered by GDK, or one of the named choices
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum Monitor {
+    Index(i32),
+    Named(MonitorChoice),
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MonitorChoice {
+    /// The monitor marked as primary, or the first one
+    Primary,
+    /// The monitor the mouse pointer is on
+    Pointer,
+    /// The monitor of the active window, falling back to the primary one
+    Focused,
+}
+
+/// The point of the monitor the window is placed against
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+    /// Along the top edge, as wide as the monitor
+    TopBar,
+}
+
+/// Maximum number of results shown per section on the Results page
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LimitsConfig {
+    pub applications: usize,
+    pub history:      usize,
+    pub programs:     usize,
+    pub files:        usize,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct HistoryConfig {
+    /// Number of commands to remember
+    pub max_length:   usize,
+
+    /// Don't record commands starting with a space, like HISTCONTROL=ignorespace
+    pub ignore_space: bool,
+
+    /// Patterns of commands not to record, see `glob.rs`
+    pub exclude:      Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FilesConfig {
+    /// Program to open files and directories with, given the path as its last argument
+    pub opener:    String,
+
+    /// Directories whose files are searched by name, for queries that aren't a path
+    pub index:     Vec<String>,
+
+    /// Patterns of file and directory names to leave out of the index, see `glob.rs`
+    pub ignore:    Vec<String>,
+
+    /// How many directories deep to index
+    pub max_depth: usize,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LaunchConfig {
+    /// File to append the output of launched commands to, instead of discarding it
+    pub log_file: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TerminalConfig {
+    /// Terminal emulator to run terminal applications in
+    pub command:  String,
+
+    /// How to run a command in the terminal, `{terminal}` and `{cmd}` are replaced
+    pub template: String,
+
+    /// Whether to keep the terminal open after the command exits
+    pub hold:     bool,
+}
+
+/// An external script providing results, see `script.rs`
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ScriptConfig {
+    /// Title of the section on the Results page, and of the tab
+    pub name:    String,
+    pub path:    String,
+    pub input:   ScriptInput,
+    pub format:  ScriptFormat,
+
+    /// Milliseconds to wait for the script before giving up
+    pub timeout: u64,
+    pub limit:   usize,
+
+    /// Whether to show all items on a page of their own too
+    pub page:    bool,
+}
+
+/// How the query is passed to a script
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptInput {
+    Argv,
+    Stdin,
+}
+
+/// What a script prints
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptFormat {
+    Lines,
+    Json,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            shell:    env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string()),
+            theme:    None,
+            window:   WindowConfig::default(),
+            launch:   LaunchConfig::default(),
+            terminal: TerminalConfig::default(),
+            limits:   LimitsConfig::default(),
+            history:  HistoryConfig::default(),
+            files:    FilesConfig::default(),
+            scripts:  vec![],
+            keys:     BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> WindowConfig {
+        WindowConfig {
+            padding:  40,
+            width:    500,
+            height:   250,
+            monitor:  Monitor::Named(MonitorChoice::Primary),
+            anchor:   Anchor::BottomLeft,
+            offset_x: 0,
+            offset_y: 0,
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> LimitsConfig {
+        LimitsConfig {
+            applications: 5,
+            history:      5,
+            programs:     10,
+            files:        10,
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> HistoryConfig {
+        HistoryConfig {
+            max_length:   50,
+            ignore_space: true,
+            exclude:      vec![],
+        }
+    }
+}
+
+impl Default for FilesConfig {
+    fn default() -> FilesConfig {
+        FilesConfig {
+            opener:    "xdg-open".to_string(),
+            index:     vec![],
+            ignore:    vec![".*".to_string(), "node_modules".to_string(), "target".to_string()],
+            max_depth: 4,
+        }
+    }
+}
+
+impl Default for TerminalConfig {
+    fn default() -> TerminalConfig {
+        TerminalConfig {
+            command:  env::var("TERMINAL").unwrap_or_else(|_| "xterm".to_string()),
+            template: "{terminal} -e {cmd}".to_string(),
+            hold:     false,
+        }
+    }
+}
+
+impl Default for ScriptConfig {
+    fn default() -> ScriptConfig {
+        ScriptConfig {
+            name:    String::new(),
+            path:    String::new(),
+            input:   ScriptInput::Argv,
+            format:  ScriptFormat::Lines,
+            timeout: 1000,
+            limit:   5,
+            page:    false,
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file, or the defaults if it doesn't exist
+    pub fn load(path: &Path) -> Result<Config, Box<Error>> {
+        let mut string = String::new();
+
+        match File::open(path) {
+            Ok(mut file) => { file.read_to_string(&mut string)?; },
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(e.into()),
+        }
+
+        let config: Config = toml::from_str(&string)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Check for values that parse fine but make no sense
+    fn validate(&self) -> Result<(), String> {
+        let mut errors = vec![];
+
+        if self.shell == "" {
+            errors.push("shell must not be empty".to_string());
+        }
+
+        if let Some(ref theme) = self.theme {
+            if theme == "" || theme.contains('/') {
+                errors.push(format!("theme must be the name of a file in the themes directory, got {:?}", theme));
+            }
+        }
+
+        if self.window.padding < 0 {
+            errors.push(format!("window.padding must not be negative, got {}", self.window.padding));
+        }
+
+        if self.window.width <= 0 || self.window.height <= 0 {
+            errors.push(format!("window size must be positive, got {}x{}", self.window.width, self.window.height));
+        }
+
+        if let Monitor::Index(index) = self.window.monitor {
+            if index < 0 {
+                errors.push(format!("window.monitor must not be negative, got {}", index));
+            }
+        }
+
+        if self.history.max_length == 0 {
+            errors.push("history.max-length must be at least 1".to_string());
+        }
+
+        if self.history.exclude.iter().any(|pattern| pattern == "") {
+            errors.push("history.exclude patterns must not be empty".to_string());
+        }
+
+        if self.files.opener.trim() == "" {
+            errors.push("files.opener must not be empty".to_string());
+        }
+
+        if !self.terminal.template.contains("{cmd}") {
+            errors.push("terminal.template must contain {cmd}".to_string());
+        }
+
+        for script in &self.scripts {
+            if script.name == "" || script.path == "" {
+                errors.push("scripts need a name and a path".to_string());
+            }
+
+            if script.timeout == 0 {
+                errors.push(format!("timeout of script {} must be at least 1", script.name));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
+
+/// Expand a leading `~` in a path from the config to the home directory
+pub fn expand_tilde(path: &str) -> String {
+    match env::var("HOME") {
+        Ok(ref home) if path == "~"            => home.clone(),
+        Ok(ref home) if path.starts_with("~/") => format!("{}{}", home, &path[1..]),
+        _                                      => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(toml: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(toml).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn missing_keys_keep_their_defaults() {
+        let config = parse("[window]\nwidth = 800\n\n[limits]\nhistory = 3").unwrap();
+
+        assert_eq!(config.window.width, 800);
+        assert_eq!(config.window.height, 250);
+        assert!(config.window.anchor == Anchor::BottomLeft);
+        assert_eq!(config.limits.history, 3);
+        assert_eq!(config.limits.programs, 10);
+    }
+
+    #[test]
+    fn monitor_by_name_or_index() {
+        assert!(parse("[window]\nmonitor = \"pointer\"").unwrap().window.monitor == Monitor::Named(MonitorChoice::Pointer));
+        assert!(parse("[window]\nmonitor = 1").unwrap().window.monitor == Monitor::Index(1));
+        assert!(parse("[window]\nmonitor = -1").is_err());
+    }
+
+    #[test]
+    fn scripts_and_keys() {
+        let config = parse(r#"
+            [keys]
+            select-next = ["ctrl+j"]
+
+            [[scripts]]
+            name   = "Power"
+            path   = "~/bin/power-menu"
+            format = "json"
+        "#).unwrap();
+
+        assert_eq!(config.keys["select-next"], vec!["ctrl+j"]);
+        assert_eq!(config.scripts[0].name, "Power");
+        assert!(config.scripts[0].format == ScriptFormat::Json);
+        assert_eq!(config.scripts[0].timeout, 1000);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_nonsense() {
+        assert!(parse("[window]\nwidht = 800").is_err());
+        assert!(parse("[window]\nwidth = 0").is_err());
+        assert!(parse("[terminal]\ntemplate = \"xterm\"").is_err());
+        assert!(parse("[[scripts]]\nname = \"Power\"").is_err());
+        assert!(parse("theme = \"../evil\"").is_err());
+    }
+
+    #[test]
+    fn expands_tilde() {
+        let home = env::var("HOME").unwrap();
+        assert_eq!(expand_tilde("~/bin"), format!("{}/bin", home));
+        assert_eq!(expand_tilde("/~/bin"), "/~/bin");
+    }
+}
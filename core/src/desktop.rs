@@ -0,0 +1,377 @@
+//! Freedesktop .desktop application entries
+//!
+//! Implements the parts of the Desktop Entry Specification needed to list
+//! and launch applications: localized keys, `Exec` field codes, `Terminal`,
+//! `NoDisplay`/`Hidden`, `OnlyShowIn`/`NotShowIn` and `TryExec`.
+
+use launch::shell_quote;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::File;
+use std::fs;
+use std::io::prelude::*;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+pub struct DesktopEntry {
+    /// Desktop file ID, e.g. `org.gnome.Nautilus.desktop`
+    pub id:           String,
+    pub name:         String,
+    pub generic_name: Option<String>,
+    pub keywords:     Vec<String>,
+    pub exec:         String,
+    pub icon:         Option<String>,
+    pub terminal:     bool,
+    pub file:         PathBuf,
+}
+
+impl DesktopEntry {
+    /// The shell command to launch this application
+    ///
+    /// Field codes in `Exec` are expanded without any files or URLs. Terminal
+    /// applications still need to be run in a terminal emulator.
+    pub fn command(&self) -> String {
+        let mut command = String::new();
+        let mut chars   = self.exec.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                command.push(c);
+                continue;
+            }
+
+            let expanded = match chars.next() {
+                Some('%') => "%".to_string(),
+                Some('i') => self.icon.as_ref().map(|icon| format!("--icon {}", shell_quote(icon))).unwrap_or_default(),
+                Some('c') => shell_quote(&self.name),
+                Some('k') => shell_quote(&self.file.to_string_lossy()),
+                // %f %F %u %U and the deprecated codes expand to nothing
+                _ => String::new(),
+            };
+
+            // A code that expands to nothing as an argument of its own goes
+            // away along with the space separating it from the others
+            let whole_argument = command.chars().last().map_or(true, char::is_whitespace) &&
+                chars.peek().map_or(true, |c| c.is_whitespace());
+
+            if expanded == "" && whole_argument && command.pop().is_none() {
+                chars.next();
+            }
+
+            command.push_str(&expanded);
+        }
+
+        command
+    }
+}
+
+/// Find all applications that should be shown, sorted by name
+pub fn get_applications() -> Vec<DesktopEntry> {
+    let desktops = env::var("XDG_CURRENT_DESKTOP")
+        .map(|d| d.split(':').map(|s| s.to_string()).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let locale = Locale::from_env();
+
+    // Earlier directories take precedence; an entry that is hidden or fails
+    // to parse still shadows entries with the same ID in later directories
+    let mut seen    = HashSet::new();
+    let mut entries = vec![];
+
+    for dir in application_dirs() {
+        for (id, file) in find_desktop_files(&dir) {
+            if !seen.insert(id.clone()) { continue }
+
+            if let Some(entry) = read_desktop_file(&id, &file, &locale, &desktops) {
+                entries.push(entry);
+            }
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.name.to_lowercase());
+    entries
+}
+
+/// `$XDG_DATA_HOME/applications` followed by `$XDG_DATA_DIRS/applications`
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = env::var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .ok().and_then(|d| if d.as_os_str().is_empty() { None } else { Some(d) })
+        .or_else(|| env::var("HOME").ok().map(|h| PathBuf::from(h).join(".local/share")));
+
+    let data_dirs = match env::var("XDG_DATA_DIRS") {
+        Ok(ref dirs) if dirs != "" => dirs.clone(),
+        _                          => "/usr/local/share:/usr/share".to_string(),
+    };
+
+    data_home.into_iter()
+        .chain(data_dirs.split(':').filter(|d| *d != "").map(PathBuf::from))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// Recursively list .desktop files in a directory along with their IDs
+fn find_desktop_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut files = vec![];
+    let mut stack = vec![(dir.to_path_buf(), String::new())];
+
+    while let Some((dir, prefix)) = stack.pop() {
+        let mut children = match fs::read_dir(&dir) {
+            Ok(children) => children.flat_map(Result::ok).collect::<Vec<_>>(),
+            Err(_)       => continue,
+        };
+
+        children.sort_by_key(|c| c.file_name());
+
+        for child in children {
+            let name = match child.file_name().into_string() {
+                Ok(name) => name,
+                Err(_)   => continue,
+            };
+
+            let path = child.path();
+
+            if path.is_dir() {
+                stack.push((path, format!("{}{}-", prefix, name)));
+            } else if name.ends_with(".desktop") {
+                files.push((format!("{}{}", prefix, name), path));
+            }
+        }
+    }
+
+    files
+}
+
+fn read_desktop_file(id: &str, file: &Path, locale: &Locale, desktops: &[String]) -> Option<DesktopEntry> {
+    let mut contents = String::new();
+    File::open(file).ok()?.read_to_string(&mut contents).ok()?;
+
+    let group = parse_group(&contents, "Desktop Entry");
+    let get   = |key: &str| group.get(key).map(|v| unescape(v));
+    let flag  = |key: &str| group.get(key).map_or(false, |v| v == "true");
+
+    if get("Type").map_or(true, |t| t != "Application") { return None }
+    if flag("NoDisplay") || flag("Hidden")               { return None }
+
+    if let Some(only) = get("OnlyShowIn") {
+        if !split_list(&only).iter().any(|d| desktops.contains(d)) { return None }
+    }
+
+    if let Some(not) = get("NotShowIn") {
+        if split_list(&not).iter().any(|d| desktops.contains(d)) { return None }
+    }
+
+    if let Some(try_exec) = get("TryExec") {
+        if !is_executable(&try_exec) { return None }
+    }
+
+    let localized = |key: &str| locale.variants(key).iter()
+        .filter_map(|k| group.get(k))
+        .map(|v| unescape(v))
+        .next();
+
+    Some(DesktopEntry {
+        id:           id.to_string(),
+        name:         localized("Name")?,
+        generic_name: localized("GenericName"),
+        keywords:     localized("Keywords").map(|k| split_list(&k)).unwrap_or_default(),
+        exec:         get("Exec")?,
+        icon:         get("Icon"),
+        terminal:     flag("Terminal"),
+        file:         file.to_path_buf(),
+    })
+}
+
+/// Parse the key-value pairs of a single group
+fn parse_group(contents: &str, name: &str) -> HashMap<String, String> {
+    let mut values   = HashMap::new();
+    let mut in_group = false;
+
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('#') || line == "" {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            in_group = &line[1 .. line.len() - 1] == name;
+            continue;
+        }
+
+        if !in_group { continue }
+
+        if let Some(eq) = line.find('=') {
+            let key   = line[.. eq].trim().to_string();
+            let value = line[eq + 1 ..].trim().to_string();
+            values.entry(key).or_insert(value);
+        }
+    }
+
+    values
+}
+
+/// Resolve the escape sequences allowed in string values
+fn unescape(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars  = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('s')  => result.push(' '),
+            Some('n')  => result.push('\n'),
+            Some('t')  => result.push('\t'),
+            Some('r')  => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            Some(c)    => { result.push('\\'); result.push(c); },
+            None       => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+/// Split a `;`-separated list value
+fn split_list(value: &str) -> Vec<String> {
+    value.split(';')
+        .map(str::trim)
+        .filter(|s| *s != "")
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn is_executable(program: &str) -> bool {
+    let executable = |path: &Path| path.metadata().ok()
+        .map_or(false, |meta| meta.is_file() && meta.permissions().mode() & 0o111 > 0);
+
+    if program.contains('/') {
+        return executable(Path::new(program));
+    }
+
+    env::var("PATH")
+        .unwrap_or_default()
+        .split(':')
+        .any(|dir| executable(&Path::new(dir).join(program)))
+}
+
+/// The user's message locale, used to pick localized keys
+struct Locale {
+    lang:     Option<String>,
+    country:  Option<String>,
+    modifier: Option<String>,
+}
+
+impl Locale {
+    fn from_env() -> Locale {
+        let value = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|v| v != "")
+            .unwrap_or_default();
+
+        Locale::parse(&value)
+    }
+
+    /// Parse a locale of the form `lang_COUNTRY.ENCODING@MODIFIER`
+    fn parse(value: &str) -> Locale {
+        let (rest, modifier) = match value.find('@') {
+            Some(i) => (&value[.. i], Some(value[i + 1 ..].to_string())),
+            None    => (value, None),
+        };
+
+        let rest = rest.split('.').next().unwrap_or("");
+
+        let (lang, country) = match rest.find('_') {
+            Some(i) => (&rest[.. i], Some(rest[i + 1 ..].to_string())),
+            None    => (rest, None),
+        };
+
+        let lang = match lang {
+            "" | "C" | "POSIX" => None,
+            lang               => Some(lang.to_string()),
+        };
+
+        Locale { lang, country, modifier }
+    }
+
+    /// Keys to look up for a localized value, in order of preference
+    fn variants(&self, key: &str) -> Vec<String> {
+        let mut variants = vec![];
+
+        if let Some(ref lang) = self.lang {
+            if let (&Some(ref country), &Some(ref modifier)) = (&self.country, &self.modifier) {
+                variants.push(format!("{}[{}_{}@{}]", key, lang, country, modifier));
+            }
+            if let Some(ref country) = self.country {
+                variants.push(format!("{}[{}_{}]", key, lang, country));
+            }
+            if let Some(ref modifier) = self.modifier {
+                variants.push(format!("{}[{}@{}]", key, lang, modifier));
+            }
+            variants.push(format!("{}[{}]", key, lang));
+        }
+
+        variants.push(key.to_string());
+        variants
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(exec: &str) -> DesktopEntry {
+        DesktopEntry {
+            id:           "editor.desktop".to_string(),
+            name:         "My Editor".to_string(),
+            generic_name: None,
+            keywords:     vec![],
+            exec:         exec.to_string(),
+            icon:         Some("editor".to_string()),
+            terminal:     false,
+            file:         PathBuf::from("/usr/share/applications/editor.desktop"),
+        }
+    }
+
+    #[test]
+    fn command_expands_field_codes() {
+        assert_eq!(entry("editor %U").command(), "editor");
+        assert_eq!(entry("editor %i --title=%c").command(), "editor --icon 'editor' --title='My Editor'");
+        assert_eq!(entry("printf 100%%").command(), "printf 100%");
+        assert_eq!(entry("%U editor --new").command(), "editor --new");
+    }
+
+    #[test]
+    fn command_keeps_quoted_whitespace() {
+        assert_eq!(entry("sh -c \"printf 'a  b'\" %F").command(), "sh -c \"printf 'a  b'\"");
+        assert_eq!(entry("editor --files=%F --new").command(), "editor --files= --new");
+    }
+
+    #[test]
+    fn parse_group_ignores_other_groups() {
+        let contents = "[Desktop Entry]\nName=Editor\n# comment\nExec=editor\n\n[Desktop Action New]\nName=New Window\n";
+        let group    = parse_group(contents, "Desktop Entry");
+
+        assert_eq!(group.get("Name").map(|s| s.as_str()), Some("Editor"));
+        assert_eq!(group.get("Exec").map(|s| s.as_str()), Some("editor"));
+        assert_eq!(group.len(), 2);
+    }
+
+    #[test]
+    fn unescape_and_split_list() {
+        assert_eq!(unescape("a\\sb\\tc\\\\"), "a b\tc\\");
+        assert_eq!(split_list("text;editor; ;"), vec!["text", "editor"]);
+    }
+
+    #[test]
+    fn localized_key_variants() {
+        let locale = Locale::parse("sr_RS.UTF-8@latin");
+
+        assert_eq!(locale.variants("Name"), vec![
+            "Name[sr_RS@latin]", "Name[sr_RS]", "Name[sr@latin]", "Name[sr]", "Name",
+        ]);
+        assert_eq!(Locale::parse("C").variants("Name"), vec!["Name"]);
+    }
+}
@@ -0,0 +1,185 @@
+//! Finding files: listing the directory of a path as it's typed, and indexing
+//! the configured directories to search them by name
+//!
+//! Paths are kept the way the user wrote them, with `~` or `./` in front, so
+//! they can be shown and completed as-is. They're only expanded to open them.
+
+use config::{self, FilesConfig};
+use glob;
+use std::fs;
+use std::path::Path;
+
+/// A file or directory found by `list`
+pub struct FileEntry {
+    /// Directory of the query followed by the name, and a `/` for directories
+    pub path: String,
+    pub name: String,
+}
+
+/// Whether the query is a path rather than something to search for
+pub fn is_path(query: &str) -> bool {
+    query == "~" || ["/", "~/", "./", "../"].iter().any(|prefix| query.starts_with(prefix))
+}
+
+/// The directory part of a path, up to and including the last `/`, and the
+/// name being typed after it
+pub fn split(path: &str) -> (&str, &str) {
+    if path == "~" {
+        return ("~", "");
+    }
+
+    match path.rfind('/') {
+        Some(i) => (&path[..i + 1], &path[i + 1..]),
+        None    => ("", path),
+    }
+}
+
+/// Everything in the directory of the path
+///
+/// Names starting with a dot are only listed when the name being typed starts
+/// with one too, like in a shell.
+pub fn list(path: &str) -> Vec<FileEntry> {
+    let (dir, typed) = split(path);
+    let dir          = if dir == "~" { "~/" } else { dir };
+
+    let entries = match fs::read_dir(config::expand_tilde(dir)) {
+        Ok(entries) => entries,
+        Err(_)      => return vec![],
+    };
+
+    entries
+        .flat_map(Result::ok)
+        .flat_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if name.starts_with('.') && !typed.starts_with('.') { return None }
+
+            // Follows symlinks, so a link to a directory can be completed into
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(FileEntry { path: format!("{}{}{}", dir, name, slash), name })
+        })
+        .collect()
+}
+
+/// The directory containing a file or directory, ending with a `/`
+pub fn parent(path: &str) -> String {
+    let path = path.trim_end_matches('/');
+
+    match path.rfind('/') {
+        Some(i) => path[..i + 1].to_string(),
+        None    => format!("{}/..", path),
+    }
+}
+
+/// The last segment of a path
+pub fn name(path: &str) -> &str {
+    split(path.trim_end_matches('/')).1
+}
+
+/// Every file and directory in the configured directories
+///
+/// Symlinks aren't followed into, so links back up the tree can't loop.
+pub fn index(config: &FilesConfig) -> Vec<String> {
+    let mut paths = vec![];
+
+    for dir in &config.index {
+        walk(Path::new(&config::expand_tilde(dir)), dir.trim_end_matches('/'), config, 1, &mut paths);
+    }
+
+    paths
+}
+
+fn walk(dir: &Path, shown: &str, config: &FilesConfig, depth: usize, paths: &mut Vec<String>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_)      => return,
+    };
+
+    for entry in entries.flat_map(Result::ok) {
+        let name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_)   => continue,
+        };
+
+        if config.ignore.iter().any(|pattern| glob::matches(pattern, &name)) { continue }
+
+        let path = format!("{}/{}", shown, name);
+
+        if entry.file_type().map_or(false, |t| t.is_dir()) {
+            paths.push(format!("{}/", path));
+
+            if depth < config.max_depth {
+                walk(&entry.path(), &path, config, depth + 1, paths);
+            }
+        } else {
+            paths.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::process;
+
+    #[test]
+    fn paths_and_searches() {
+        assert!(is_path("/etc"));
+        assert!(is_path("~"));
+        assert!(is_path("~/src"));
+        assert!(is_path("./notes.txt"));
+
+        assert!(!is_path("~user"));
+        assert!(!is_path("firefox"));
+        assert!(!is_path("."));
+    }
+
+    #[test]
+    fn split_and_parent() {
+        assert_eq!(split("~/src/infl"), ("~/src/", "infl"));
+        assert_eq!(split("/"), ("/", ""));
+        assert_eq!(split("~"), ("~", ""));
+
+        assert_eq!(parent("~/src/influence/"), "~/src/");
+        assert_eq!(parent("/etc/hosts"), "/etc/");
+        assert_eq!(parent("./notes.txt"), "./");
+
+        assert_eq!(name("~/src/influence/"), "influence");
+        assert_eq!(name("/etc/hosts"), "hosts");
+    }
+
+    #[test]
+    fn list_and_index() {
+        let dir = env::temp_dir().join(format!("influence-test-files-{}", process::id()));
+        let _   = fs::remove_dir_all(&dir);
+
+        fs::create_dir_all(dir.join("src/deep/deeper")).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        File::create(dir.join("notes.txt")).unwrap();
+        File::create(dir.join("src/main.rs")).unwrap();
+        File::create(dir.join("src/deep/deeper/lost.rs")).unwrap();
+
+        let shown = dir.to_str().unwrap();
+
+        let mut listed = list(&format!("{}/", shown)).into_iter().map(|e| e.name).collect::<Vec<_>>();
+        listed.sort();
+        assert_eq!(listed, vec!["notes.txt", "src"]);
+
+        let mut hidden = list(&format!("{}/.g", shown)).into_iter().map(|e| e.path).collect::<Vec<_>>();
+        hidden.sort();
+        assert_eq!(hidden, vec![format!("{}/.git/", shown), format!("{}/notes.txt", shown), format!("{}/src/", shown)]);
+
+        let config = FilesConfig { index: vec![shown.to_string()], max_depth: 2, ..Default::default() };
+        let mut indexed = index(&config);
+        indexed.sort();
+        assert_eq!(indexed, vec![
+            format!("{}/notes.txt", shown),
+            format!("{}/src/", shown),
+            format!("{}/src/deep/", shown),
+            format!("{}/src/main.rs", shown),
+        ]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
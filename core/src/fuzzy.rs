@@ -0,0 +1,237 @@
+//! Fuzzy subsequence matching
+//!
+//! A pattern matches a candidate when all of its characters appear in the
+//! candidate in the same order. Matches are scored so that characters at the
+//! start of words and runs of consecutive characters rank higher than matches
+//! scattered across the candidate.
+
+const SCORE_MATCH:       i64 = 16;
+const BONUS_WORD_START:  i64 = 24;
+const BONUS_FIRST_CHAR:  i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 16;
+const BONUS_EXACT_CASE:  i64 = 1;
+const PENALTY_GAP:       i64 = 2;
+const PENALTY_LEADING:   i64 = 1;
+const MAX_LEADING:       i64 = 8;
+
+/// Score a candidate against a pattern, or `None` if it doesn't match
+///
+/// Matching is case-insensitive; characters matching with the exact same case
+/// get a small bonus. An empty pattern matches everything with a score of 0.
+pub fn score(pattern: &str, candidate: &str) -> Option<i64> {
+    let pattern   = pattern.chars().collect::<Vec<char>>();
+    let candidate = candidate.chars().collect::<Vec<char>>();
+
+    if pattern.is_empty() {
+        return Some(0);
+    }
+
+    let matrix = score_matrix(&pattern, &candidate)?;
+    matrix.last().unwrap().iter().flat_map(|&s| s).max()
+}
+
+/// Character positions in the candidate of the best match, or `None` if it
+/// doesn't match
+///
+/// Positions count characters, not bytes. An empty pattern matches without
+/// any positions.
+pub fn positions(pattern: &str, candidate: &str) -> Option<Vec<usize>> {
+    let pattern   = pattern.chars().collect::<Vec<char>>();
+    let candidate = candidate.chars().collect::<Vec<char>>();
+
+    if pattern.is_empty() {
+        return Some(vec![]);
+    }
+
+    let matrix  = score_matrix(&pattern, &candidate)?;
+    let bonuses = position_bonuses(&candidate);
+
+    let last  = pattern.len() - 1;
+    let (mut j, _) = matrix[last].iter().enumerate()
+        .filter_map(|(j, &s)| s.map(|s| (j, s)))
+        .max_by_key(|&(j, s)| (s, -(j as i64)))?;
+
+    let mut positions = vec![j];
+
+    // Walk back through the matrix, finding the match of the previous
+    // character that the score at each step was built on
+    for i in (1 .. pattern.len()).rev() {
+        let target = matrix[i][j].unwrap() - char_score(pattern[i], candidate[j], bonuses[j]);
+
+        let consecutive = if j >= 1 {
+            matrix[i - 1][j - 1].map(|s| s + BONUS_CONSECUTIVE)
+        } else {
+            None
+        };
+
+        j = if consecutive == Some(target) {
+            j - 1
+        } else {
+            (0 .. j.saturating_sub(1)).rev()
+                .find(|&k| matrix[i - 1][k].map(|s| s - (j - k - 1) as i64 * PENALTY_GAP) == Some(target))
+                .unwrap()
+        };
+
+        positions.push(j);
+    }
+
+    positions.reverse();
+    Some(positions)
+}
+
+/// Best score for each pattern character matched at each candidate position
+///
+/// `matrix[i][j]` is the best score of the first `i + 1` pattern characters
+/// with the last one matched at `j`, or `None` if that's impossible.
+fn score_matrix(pattern: &[char], candidate: &[char]) -> Option<Vec<Vec<Option<i64>>>> {
+    if pattern.len() > candidate.len() || !is_subsequence(pattern, candidate) {
+        return None;
+    }
+
+    let bonuses = position_bonuses(candidate);
+    let mut matrix: Vec<Vec<Option<i64>>> = vec![];
+
+    for (i, &p) in pattern.iter().enumerate() {
+        let mut cur = vec![None; candidate.len()];
+
+        // Best score of a previous match that is followed by a gap, with the
+        // gap penalty up to the current position folded in incrementally
+        let mut best_gapped: Option<i64> = None;
+
+        for (j, &c) in candidate.iter().enumerate() {
+            if i >= 1 && j >= 1 {
+                let prev = &matrix[i - 1];
+                best_gapped = best_gapped.map(|s| s - PENALTY_GAP);
+                if j >= 2 {
+                    best_gapped = max_opt(best_gapped, prev[j - 2].map(|s| s - PENALTY_GAP));
+                }
+            }
+
+            if !chars_eq(p, c) {
+                continue;
+            }
+
+            let char_score = char_score(p, c, bonuses[j]);
+
+            cur[j] = if i == 0 {
+                Some(char_score - (j as i64 * PENALTY_LEADING).min(MAX_LEADING))
+            } else {
+                let consecutive = if j >= 1 {
+                    matrix[i - 1][j - 1].map(|s| s + BONUS_CONSECUTIVE)
+                } else {
+                    None
+                };
+
+                max_opt(consecutive, best_gapped).map(|s| s + char_score)
+            };
+        }
+
+        matrix.push(cur);
+    }
+
+    Some(matrix)
+}
+
+fn char_score(p: char, c: char, bonus: i64) -> i64 {
+    SCORE_MATCH + bonus + if p == c { BONUS_EXACT_CASE } else { 0 }
+}
+
+fn position_bonuses(candidate: &[char]) -> Vec<i64> {
+    (0 .. candidate.len())
+        .map(|i| position_bonus(candidate, i))
+        .collect()
+}
+
+/// Best score of a pattern against any of several fields of a candidate
+pub fn best_score<I, S>(pattern: &str, fields: I) -> Option<i64>
+    where I: IntoIterator<Item = S>,
+          S: AsRef<str>
+{
+    fields.into_iter()
+        .filter_map(|field| score(pattern, field.as_ref()))
+        .max()
+}
+
+/// Bonus for matching the character at the given position
+fn position_bonus(candidate: &[char], i: usize) -> i64 {
+    if i == 0 {
+        return BONUS_WORD_START + BONUS_FIRST_CHAR;
+    }
+
+    let prev = candidate[i - 1];
+    let cur  = candidate[i];
+
+    if (is_separator(prev) && !is_separator(cur)) || (prev.is_lowercase() && cur.is_uppercase()) {
+        BONUS_WORD_START
+    } else if !prev.is_numeric() && cur.is_numeric() {
+        BONUS_WORD_START / 2
+    } else {
+        0
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || "-_./:,;=@+".contains(c)
+}
+
+fn is_subsequence(pattern: &[char], candidate: &[char]) -> bool {
+    let mut pattern = pattern.iter().peekable();
+
+    for &c in candidate {
+        match pattern.peek() {
+            Some(&&p) if chars_eq(p, c) => { pattern.next(); },
+            Some(_)                    => (),
+            None                       => break,
+        }
+    }
+
+    pattern.peek().is_none()
+}
+
+fn chars_eq(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+fn max_opt(a: Option<i64>, b: Option<i64>) -> Option<i64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, None)          => a,
+        (None, b)          => b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subsequences_only() {
+        assert!(score("ffx", "firefox").is_some());
+        assert!(score("FIRE", "firefox").is_some());
+        assert!(score("xff", "firefox").is_none());
+        assert_eq!(score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn prefers_word_starts_and_runs() {
+        assert!(score("gc", "google-chrome") > score("gc", "magic"));
+        assert!(score("ab", "xaby") > score("ab", "xaxb"));
+        assert!(score("Fire", "Firefox") > score("Fire", "firefox"));
+    }
+
+    #[test]
+    fn positions_of_best_match() {
+        assert_eq!(positions("gc", "google-chrome"), Some(vec![0, 7]));
+        assert_eq!(positions("rome", "google-chrome"), Some(vec![9, 10, 11, 12]));
+        assert_eq!(positions("é", "café"), Some(vec![3]));
+        assert_eq!(positions("z", "firefox"), None);
+        assert_eq!(positions("", "firefox"), Some(vec![]));
+    }
+
+    #[test]
+    fn best_score_of_all_fields() {
+        let fields = ["Web Browser", "Firefox"];
+        assert_eq!(best_score("fire", fields.iter()), score("fire", "Firefox"));
+        assert_eq!(best_score("zzz", fields.iter()), None);
+    }
+}
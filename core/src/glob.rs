@@ -0,0 +1,92 @@
+//! Shell-style wildcard patterns for matching whole commands
+//!
+//! `*` matches any text, including none, `?` matches a single character and
+//! a backslash matches the character after it literally.
+
+#[derive(PartialEq)]
+enum Token {
+    Star,
+    Any,
+    Char(char),
+}
+
+fn tokens(pattern: &str) -> Vec<Token> {
+    let mut chars  = pattern.chars();
+    let mut tokens = vec![];
+
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '*'  => Token::Star,
+            '?'  => Token::Any,
+            '\\' => Token::Char(chars.next().unwrap_or('\\')),
+            c    => Token::Char(c),
+        });
+    }
+
+    tokens
+}
+
+/// Whether the pattern matches all of the text
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern = tokens(pattern);
+    let text    = text.chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+
+    // Where to resume after the last star when the rest doesn't match:
+    // the token after the star, and the text it was tried at
+    let mut retry = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(&Token::Star) => {
+                p += 1;
+                retry = Some((p, t));
+            },
+            Some(&Token::Any) => {
+                p += 1;
+                t += 1;
+            },
+            Some(&Token::Char(c)) if c == text[t] => {
+                p += 1;
+                t += 1;
+            },
+            _ => match retry {
+                // Let the star take one more character
+                Some((after_star, tried)) => {
+                    p     = after_star;
+                    t     = tried + 1;
+                    retry = Some((after_star, t));
+                },
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|token| *token == Token::Star)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("pass *", "pass show email"));
+        assert!(matches("*token=*", "curl https://x?token=abc&y=1"));
+        assert!(matches("*", ""));
+        assert!(matches("ss?", "ssh"));
+        assert!(matches("*a*b*", "xaxxbx"));
+
+        assert!(!matches("pass *", "passwd"));
+        assert!(!matches("ss?", "ss"));
+        assert!(!matches("*a*b", "xaxbx"));
+        assert!(!matches("htop", "htop -d 5"));
+    }
+
+    #[test]
+    fn escaped_wildcards_are_literal() {
+        assert!(matches("echo \\*", "echo *"));
+        assert!(!matches("echo \\*", "echo hi"));
+    }
+}
@@ -0,0 +1,254 @@
+//! Command history with launch counts and frecency ranking
+//!
+//! History is stored one entry per line as
+//! `count<TAB>last_used<TAB>terminal<TAB>command`, preceded by a version
+//! header, where `terminal` is `1` for commands run in a terminal emulator.
+//! Version 2 files have no `terminal` field. Files without a header are read
+//! as the oldest format, which is a plain list of commands, most recent first.
+
+use config::HistoryConfig;
+use glob;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HEADER:    &str = "# influence history v3";
+const HEADER_V2: &str = "# influence history v2";
+
+const HOUR: u64 = 60 * 60;
+const DAY:  u64 = 24 * HOUR;
+
+pub struct HistoryEntry {
+    pub command:   String,
+
+    /// Number of times this command was launched
+    pub count:     u32,
+
+    /// Unix timestamp of the last launch, in seconds
+    pub last_used: u64,
+
+    /// Whether the command was last run in a terminal emulator
+    pub terminal:  bool,
+}
+
+impl HistoryEntry {
+    /// Launch count weighted by how recently the command was used
+    pub fn frecency(&self, now: u64) -> u64 {
+        let age = now.saturating_sub(self.last_used);
+
+        let weight = match age {
+            a if a <  4 * HOUR => 120,
+            a if a <  4 * DAY  => 100,
+            a if a < 14 * DAY  =>  70,
+            a if a < 31 * DAY  =>  50,
+            a if a < 90 * DAY  =>  30,
+            _                  =>  10,
+        };
+
+        self.count as u64 * weight
+    }
+
+    /// Bonus added to a fuzzy match score, growing logarithmically with frecency
+    pub fn rank_bonus(&self, now: u64) -> i64 {
+        let frecency = self.frecency(now);
+        4 * (64 - frecency.leading_zeros() as i64)
+    }
+}
+
+/// Current time as a Unix timestamp in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Parse history from the lines of history.txt, in either format
+pub fn parse(lines: Vec<String>, now: u64) -> Vec<HistoryEntry> {
+    let version = match lines.first().map(|l| l.as_str()) {
+        Some(HEADER)    => 3,
+        Some(HEADER_V2) => 2,
+        _               => 1,
+    };
+
+    let mut entries = if version > 1 {
+        lines.iter()
+            .skip(1)
+            .filter_map(|line| {
+                let mut fields = line.splitn(if version == 3 { 4 } else { 3 }, '\t');
+                let count      = fields.next()?.parse().ok()?;
+                let last_used  = fields.next()?.parse().ok()?;
+                let terminal   = version == 3 && fields.next()? == "1";
+                let command    = fields.next()?.to_string();
+                Some(HistoryEntry { command, count, last_used, terminal })
+            })
+            .collect::<Vec<_>>()
+    } else {
+        // Old format: keep the existing order by spacing the entries one
+        // second apart, most recent first
+        lines.into_iter()
+            .filter(|l| l != "")
+            .enumerate()
+            .map(|(i, command)| HistoryEntry {
+                command,
+                count:     1,
+                last_used: now.saturating_sub(i as u64),
+                terminal:  false,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    sort(&mut entries, now);
+    entries
+}
+
+/// Serialize history into lines for history.txt
+pub fn serialize(entries: &[HistoryEntry]) -> Vec<String> {
+    let mut lines = vec![HEADER.to_string()];
+
+    lines.extend(entries.iter().map(|e| format!("{}\t{}\t{}\t{}", e.count, e.last_used, e.terminal as u8, e.command)));
+
+    lines
+}
+
+/// Record a launch of a command, keeping at most `maxlen` entries
+///
+/// When the history is full, the entries with the lowest frecency are
+/// dropped, but never the command that was just launched.
+pub fn record(entries: &mut Vec<HistoryEntry>, command: &str, terminal: bool, now: u64, maxlen: usize) {
+    match entries.iter_mut().find(|e| e.command == command) {
+        Some(entry) => {
+            entry.count    += 1;
+            entry.last_used = now;
+            entry.terminal  = terminal;
+        },
+        None => entries.push(HistoryEntry {
+            command:   command.to_string(),
+            count:     1,
+            last_used: now,
+            terminal,
+        }),
+    }
+
+    sort(entries, now);
+
+    while entries.len() > maxlen {
+        match entries.iter().rposition(|e| e.command != command) {
+            Some(i) => { entries.remove(i); },
+            None    => break,
+        }
+    }
+}
+
+/// Whether a launch of the command should be recorded, or is excluded by the
+/// config because it might contain a secret
+pub fn should_record(config: &HistoryConfig, command: &str) -> bool {
+    if config.ignore_space && command.starts_with(' ') { return false }
+
+    !config.exclude.iter().any(|pattern| glob::matches(pattern, command))
+}
+
+/// Remove the entries matching the pattern, returning how many there were
+pub fn purge(entries: &mut Vec<HistoryEntry>, pattern: &str) -> usize {
+    let before = entries.len();
+    entries.retain(|e| !glob::matches(pattern, &e.command));
+    before - entries.len()
+}
+
+/// Sort entries by frecency, highest first
+pub fn sort(entries: &mut [HistoryEntry], now: u64) {
+    entries.sort_by(|a, b| {
+        b.frecency(now).cmp(&a.frecency(now))
+            .then(b.last_used.cmp(&a.last_used))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(entries: &[HistoryEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.command.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_old_format_most_recent_first() {
+        let lines   = vec!["make".to_string(), "".to_string(), "htop".to_string()];
+        let entries = parse(lines, 1000);
+
+        assert_eq!(commands(&entries), vec!["make", "htop"]);
+        assert_eq!(entries[0].last_used, 1000);
+        assert_eq!(entries[1].last_used, 999);
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let mut entries = vec![];
+        record(&mut entries, "make", false, 1000, 10);
+        record(&mut entries, "make", false, 1001, 10);
+        record(&mut entries, "cargo test", false, 1002, 10);
+
+        let parsed = parse(serialize(&entries), 1002);
+        assert_eq!(commands(&parsed), vec!["make", "cargo test"]);
+        assert_eq!(parsed[0].count, 2);
+        assert_eq!(parsed[0].last_used, 1001);
+    }
+
+    #[test]
+    fn terminal_flag_round_trip() {
+        let mut entries = vec![];
+        record(&mut entries, "htop", true, 1000, 10);
+
+        let parsed = parse(serialize(&entries), 1000);
+        assert!(parsed[0].terminal);
+
+        let v2 = vec![HEADER_V2.to_string(), "3\t1000\tmake test".to_string()];
+        let parsed = parse(v2, 1000);
+        assert_eq!(commands(&parsed), vec!["make test"]);
+        assert_eq!(parsed[0].count, 3);
+        assert!(!parsed[0].terminal);
+    }
+
+    #[test]
+    fn record_drops_lowest_frecency_but_not_the_new_command() {
+        let mut entries = vec![];
+        record(&mut entries, "a", false, 1000, 2);
+        record(&mut entries, "a", false, 1000, 2);
+        record(&mut entries, "b", false, 1000, 2);
+        record(&mut entries, "c", false, 1001, 2);
+
+        assert_eq!(commands(&entries), vec!["a", "c"]);
+    }
+
+    #[test]
+    fn excluded_commands_are_not_recorded() {
+        let config = HistoryConfig { exclude: vec!["pass *".to_string(), "*token=*".to_string()], ..Default::default() };
+
+        assert!(should_record(&config, "htop"));
+        assert!(!should_record(&config, " htop"));
+        assert!(!should_record(&config, "pass show email"));
+        assert!(!should_record(&config, "curl example.com?token=abc"));
+
+        let config = HistoryConfig { ignore_space: false, ..Default::default() };
+        assert!(should_record(&config, " htop"));
+    }
+
+    #[test]
+    fn purge_removes_matching_entries() {
+        let mut entries = vec![];
+        record(&mut entries, "ssh work", false, 1000, 10);
+        record(&mut entries, "htop", false, 1000, 10);
+        record(&mut entries, "ssh home", false, 1000, 10);
+
+        assert_eq!(purge(&mut entries, "ssh *"), 2);
+        assert_eq!(commands(&entries), vec!["htop"]);
+    }
+
+    #[test]
+    fn recent_commands_rank_higher() {
+        let now    = 100 * DAY;
+        let recent = HistoryEntry { command: "a".to_string(), count: 3, last_used: now - HOUR, terminal: false };
+        let old    = HistoryEntry { command: "b".to_string(), count: 3, last_used: now - 60 * DAY, terminal: false };
+
+        assert!(recent.frecency(now) > old.frecency(now));
+        assert!(recent.rank_bonus(now) > old.rank_bonus(now));
+    }
+}
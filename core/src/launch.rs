@@ -0,0 +1,229 @@
+//! Starting commands in the background
+//!
+//! Simple commands, a program followed by plain or quoted arguments, are run
+//! directly. Anything using shell syntax, such as pipes, redirections,
+//! variables or globs, is handed to the configured shell instead.
+//!
+//! Commands are started in a session of their own, so they survive influence
+//! exiting, and with their output discarded or appended to a log file.
+
+use config::{self, Config};
+use libc;
+use std::fs::OpenOptions;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Characters that have a special meaning to the shell outside of quotes
+const SHELL_CHARS: &str = "|&;<>()$`*?[]{}!\\\n";
+
+/// Start a command without waiting for it
+///
+/// Errors are only reported for commands that couldn't be started at all,
+/// not for commands that started and failed later on.
+pub fn spawn(cmd: &str, config: &Config) -> Result<(), String> {
+    let (mut command, program) = match command(cmd, config) {
+        Some(command) => command,
+        None          => return Ok(()),
+    };
+
+    let (stdout, stderr) = match config.launch.log_file {
+        Some(ref path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(config::expand_tilde(path))
+                .map_err(|e| format!("unable to open {}: {}", path, e))?;
+
+            let clone = file.try_clone().map_err(|e| e.to_string())?;
+            (Stdio::from(file), Stdio::from(clone))
+        },
+        None => (Stdio::null(), Stdio::null()),
+    };
+
+    command
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(stderr);
+
+    // Leave influence's session, so the command isn't killed along with it
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+
+    let mut child = command.spawn().map_err(|e| spawn_error(&program, &e))?;
+
+    // Reap the child when it exits, so the daemon doesn't collect zombies
+    thread::spawn(move || child.wait());
+
+    Ok(())
+}
+
+/// The process to start for a command and the name of its program, or None
+/// if there is nothing to run
+pub fn command(cmd: &str, config: &Config) -> Option<(Command, String)> {
+    match split(cmd) {
+        Some(ref words) if words.is_empty() => None,
+        Some(words) => {
+            let mut command = Command::new(&words[0]);
+            command.args(&words[1..]);
+            Some((command, words[0].clone()))
+        },
+        None => {
+            let mut command = Command::new(&config.shell);
+            command.arg("-c").arg(cmd);
+            Some((command, config.shell.clone()))
+        },
+    }
+}
+
+/// Open a file or directory with the configured opener
+pub fn open(path: &str, config: &Config) -> Result<(), String> {
+    let path = config::expand_tilde(path);
+    spawn(&format!("{} {}", config.files.opener, shell_quote(&path)), config)
+}
+
+/// Describe why a program couldn't be started
+pub fn spawn_error(program: &str, e: &io::Error) -> String {
+    match e.kind() {
+        io::ErrorKind::NotFound         => format!("command not found: {}", program),
+        io::ErrorKind::PermissionDenied => format!("permission denied: {}", program),
+        _                               => format!("unable to run {}: {}", program, e),
+    }
+}
+
+/// Wrap a command so it runs in the configured terminal emulator
+///
+/// The command goes through the shell if it needs one, or if the terminal
+/// should stay open after it exits.
+pub fn in_terminal(cmd: &str, config: &Config) -> String {
+    let terminal = &config.terminal;
+
+    let args = match split(cmd) {
+        Some(ref words) if !terminal.hold => {
+            words.iter().map(|w| shell_quote(w)).collect::<Vec<_>>().join(" ")
+        },
+        _ => {
+            let script = if terminal.hold {
+                format!("{}\nprintf '\\n[exited with status %d, press enter to close]' $?; read _", cmd)
+            } else {
+                cmd.to_string()
+            };
+
+            format!("{} -c {}", shell_quote(&config.shell), shell_quote(&script))
+        },
+    };
+
+    terminal.template
+        .replace("{terminal}", &terminal.command)
+        .replace("{cmd}", &args)
+}
+
+/// Split a simple command into words, or None if it needs a shell
+///
+/// Single and double quotes group words as in the shell. A `~` or `#` at the
+/// start of a word, an `=` in the first word (a variable assignment) or any of
+/// `SHELL_CHARS` outside of single quotes all mean the shell is needed.
+fn split(cmd: &str) -> Option<Vec<String>> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = cmd.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' => {
+                if let Some(word) = word.take() {
+                    words.push(word);
+                }
+            },
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c)    => word.push(c),
+                        None       => return None,
+                    }
+                }
+            },
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"')                     => break,
+                        Some(c) if "$`\\".contains(c) => return None,
+                        Some(c)                       => word.push(c),
+                        None                          => return None,
+                    }
+                }
+            },
+            '~' | '#' if word.is_none()  => return None,
+            '=' if words.is_empty()      => return None,
+            c if SHELL_CHARS.contains(c) => return None,
+            c                            => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(word) = word {
+        words.push(word);
+    }
+
+    Some(words)
+}
+
+/// Quote a word so the shell passes it on as-is
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(cmd: &str) -> Option<Vec<String>> {
+        split(cmd)
+    }
+
+    #[test]
+    fn splits_simple_commands() {
+        assert_eq!(words("firefox --new-window"), Some(vec!["firefox".to_string(), "--new-window".to_string()]));
+        assert_eq!(words("echo 'a b' \"c d\"e"), Some(vec!["echo".to_string(), "a b".to_string(), "c de".to_string()]));
+        assert_eq!(words("   "), Some(vec![]));
+    }
+
+    #[test]
+    fn leaves_shell_syntax_to_the_shell() {
+        for cmd in &["ls | less", "echo $HOME", "cd ~/src", "FOO=1 make", "rm *.o", "echo \"$x\"", "a && b", "'unclosed"] {
+            assert_eq!(words(cmd), None, "{}", cmd);
+        }
+
+        // Quoted shell characters are fine
+        assert!(words("echo '$HOME | less'").is_some());
+    }
+
+    #[test]
+    fn wraps_commands_for_the_terminal() {
+        let mut config = Config {
+            shell:    "/bin/sh".to_string(),
+            terminal: config::TerminalConfig { command: "st".to_string(), ..Default::default() },
+            ..Default::default()
+        };
+
+        assert_eq!(in_terminal("htop -d 10", &config), "st -e 'htop' '-d' '10'");
+        assert_eq!(in_terminal("ls | less", &config), "st -e '/bin/sh' -c 'ls | less'");
+
+        config.terminal.template = "{terminal} --hold -- {cmd}".to_string();
+        assert_eq!(in_terminal("htop", &config), "st --hold -- 'htop'");
+    }
+
+    #[test]
+    fn spawn_errors() {
+        let e = spawn("influence-test-no-such-program", &Config::default()).unwrap_err();
+        assert_eq!(e, "command not found: influence-test-no-such-program");
+        assert_eq!(spawn("", &Config::default()), Ok(()));
+    }
+}
@@ -0,0 +1,213 @@
+//! Everything influence does that doesn't need a display: the config,
+//! bookmarks, history, the providers and their fuzzy matching, and running
+//! commands
+//!
+//! The GTK frontend is a thin layer on top of this crate, and other tools can
+//! use it to search the same bookmarks, history and programs.
+
+// Written for the 2015 edition and the compilers of its time
+#![allow(bare_trait_objects)]
+#![allow(clippy::comparison_to_empty, clippy::match_like_matches_macro)]
+#![allow(clippy::needless_borrowed_reference, clippy::unnecessary_map_or)]
+
+extern crate libc;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+
+pub mod bookmark;
+pub mod calc;
+pub mod config;
+pub mod desktop;
+pub mod files;
+pub mod fuzzy;
+pub mod glob;
+pub mod history;
+pub mod launch;
+pub mod output;
+pub mod provider;
+pub mod script;
+pub mod store;
+
+use bookmark::{Bookmark, Entry};
+use config::Config;
+use desktop::DesktopEntry;
+use history::HistoryEntry;
+use std::error::Error;
+
+pub const BOOKMARKS_LABEL:    &str = "Bookmarks";
+pub const HISTORY_LABEL:      &str = "History";
+pub const RESULTS_LABEL:      &str = "Results";
+pub const PROGRAMS_LABEL:     &str = "Programs";
+pub const APPLICATIONS_LABEL: &str = "Applications";
+pub const ITEMS_LABEL:        &str = "Items";
+pub const CALCULATOR_LABEL:   &str = "Calculator";
+pub const OUTPUT_LABEL:       &str = "Output";
+pub const FILES_LABEL:        &str = "Files";
+
+/// What to do when a row is chosen
+#[derive(Clone, PartialEq, Debug)]
+pub enum Activation {
+    Run(String),           // run the command
+    Terminal(String),      // run the command in a terminal emulator
+    Copy(String),          // copy the text to the clipboard
+    Select(usize, String), // send the text back to the script with this index
+    Open(String),          // open the file or directory with the configured opener
+}
+
+impl Activation {
+    /// Run the command, in a terminal if it needs one
+    pub fn run(command: String, terminal: bool) -> Activation {
+        if terminal {
+            Activation::Terminal(command)
+        } else {
+            Activation::Run(command)
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match *self {
+            Activation::Run(ref s)      |
+            Activation::Terminal(ref s) |
+            Activation::Copy(ref s)     |
+            Activation::Open(ref s)     |
+            Activation::Select(_, ref s) => s,
+        }
+    }
+}
+
+/// Everything the providers search through
+#[derive(Default)]
+pub struct Model {
+    pub config:       Config,
+    pub bookmarks:    Vec<Entry>,
+    pub history:      Vec<HistoryEntry>,
+    pub programs:     Vec<String>,
+    pub applications: Vec<DesktopEntry>,
+
+    /// Files and directories in the indexed directories
+    pub files:        Vec<String>,
+
+    /// Items read from stdin in dmenu mode
+    pub items:        Vec<String>,
+}
+
+impl Model {
+    /// Load the bookmarks, history, programs and applications of the user
+    pub fn load(config: Config) -> Model {
+        Model {
+            bookmarks:    store::load_bookmarks(),
+            history:      store::load_history(),
+            programs:     store::get_path_commands(),
+            applications: desktop::get_applications(),
+            files:        files::index(&config.files),
+            items:        vec![],
+            config,
+        }
+    }
+
+    /// A model with only the items to choose from in dmenu mode
+    pub fn dmenu(config: Config, items: Vec<String>) -> Model {
+        Model { config, items, ..Default::default() }
+    }
+
+    /// Record a launch of the command and save the history, unless the config
+    /// excludes it
+    ///
+    /// The history is merged with launches other instances saved in the
+    /// meantime. If it can't be saved, the launch is still recorded here.
+    pub fn record_history(&mut self, command: &str, terminal: bool) -> Result<(), Box<Error>> {
+        if !history::should_record(&self.config.history, command) { return Ok(()) }
+
+        let (now, maxlen) = (history::now(), self.config.history.max_length);
+        let record = |entries: &mut Vec<HistoryEntry>| history::record(entries, command, terminal, now, maxlen);
+
+        self.update_history(record)
+    }
+
+    /// Remove the history entry at the index, here and from the saved history
+    pub fn remove_history_entry(&mut self, index: usize) -> Result<(), Box<Error>> {
+        let command = match self.history.get(index) {
+            Some(entry) => entry.command.clone(),
+            None        => return Ok(()),
+        };
+
+        self.update_history(|entries: &mut Vec<HistoryEntry>| entries.retain(|e| e.command != command))
+    }
+
+    fn update_history<F: Fn(&mut Vec<HistoryEntry>)>(&mut self, change: F) -> Result<(), Box<Error>> {
+        match store::update_history(&change) {
+            Ok(entries) => {
+                self.history = entries;
+                Ok(())
+            },
+            Err(e) => {
+                change(&mut self.history);
+                Err(e)
+            },
+        }
+    }
+
+    /// Add a bookmark at the end of the list, unless the command is already
+    /// bookmarked or can't be one
+    ///
+    /// Returns whether the bookmark was added. The bookmarks aren't saved, so
+    /// several edits can be made before calling `save_bookmarks`.
+    pub fn add_bookmark(&mut self, command: &str, terminal: bool) -> bool {
+        let command = command.trim();
+
+        // Lines starting with # are headers, so they can't be bookmarked
+        if command == "" || command.starts_with('#') { return false }
+
+        let exists = self.bookmarks.iter().any(|e| match *e {
+            Entry::Bookmark(ref b) => b.command == command,
+            _                      => false,
+        });
+
+        if exists { return false }
+
+        self.bookmarks.push(Entry::Bookmark(Bookmark {
+            command: command.to_string(),
+            terminal,
+            ..Default::default()
+        }));
+
+        true
+    }
+
+    pub fn save_bookmarks(&self) -> Result<(), Box<Error>> {
+        store::write_file_list(store::FileStore::Bookmarks, &bookmark::serialize(&self.bookmarks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_bookmark_skips_duplicates_and_headers() {
+        let mut model = Model::default();
+
+        assert!(model.add_bookmark(" htop ", true));
+        assert!(!model.add_bookmark("htop", false));
+        assert!(!model.add_bookmark("# header", false));
+        assert!(!model.add_bookmark("   ", false));
+
+        assert_eq!(model.bookmarks.len(), 1);
+        match model.bookmarks[0] {
+            Entry::Bookmark(ref b) => {
+                assert_eq!(b.command, "htop");
+                assert!(b.terminal);
+            },
+            _ => panic!("expected a bookmark"),
+        }
+    }
+
+    #[test]
+    fn activation_text() {
+        assert_eq!(Activation::run("htop".to_string(), true), Activation::Terminal("htop".to_string()));
+        assert_eq!(Activation::Select(2, "suspend".to_string()).text(), "suspend");
+    }
+}
@@ -0,0 +1,183 @@
+//! Running a command and capturing its output for the Output page
+
+use config::Config;
+use launch;
+use libc;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::mem;
+use std::os::unix::process::CommandExt;
+use std::process::Stdio;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+/// Something that happened to a command started by `run`
+pub enum Event {
+    Stdout(String),      // a line of output, including the newline
+    Stderr(String),      // a line of error output
+    Exited(Option<i32>), // exit status, or None if killed by a signal
+    Failed(String),      // the command couldn't be waited for
+}
+
+impl Event {
+    /// Whether this is the last event for the command
+    pub fn is_final(&self) -> bool {
+        match *self {
+            Event::Exited(_) | Event::Failed(_) => true,
+            _                                   => false,
+        }
+    }
+}
+
+/// A command started by `run`, which can be stopped before it exits
+pub struct Process {
+    /// Process group of the command, and whether it has been reaped, after
+    /// which the ID may belong to another process
+    group: Option<(i32, Arc<Mutex<bool>>)>,
+}
+
+impl Process {
+    /// Stop the command and everything it started, if it's still running
+    pub fn kill(&self) {
+        if let Some((pid, ref reaped)) = self.group {
+            let reaped = reaped.lock().unwrap();
+            if !*reaped {
+                unsafe { libc::kill(-pid, libc::SIGTERM); }
+            }
+        }
+    }
+}
+
+/// Start a command, sending its output through the returned channel as it
+/// comes in, followed by its exit status
+///
+/// Fails with a message if the command couldn't be started.
+pub fn run(cmd: &str, config: &Config) -> Result<(Process, mpsc::Receiver<Event>), String> {
+    let (sender, receiver) = mpsc::channel();
+
+    let (mut command, program) = match launch::command(cmd, config) {
+        Some(command) => command,
+        None          => {
+            let _ = sender.send(Event::Exited(Some(0)));
+            return Ok((Process { group: None }, receiver));
+        },
+    };
+
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    // A group of its own, so that killing it also stops the commands a shell
+    // started for it
+    unsafe {
+        command.pre_exec(|| {
+            libc::setpgid(0, 0);
+            Ok(())
+        });
+    }
+
+    let mut child = command.spawn().map_err(|e| launch::spawn_error(&program, &e))?;
+
+    let pid    = child.id() as i32;
+    let reaped = Arc::new(Mutex::new(false));
+
+    let stdout = read_lines(child.stdout.take().unwrap(), sender.clone(), Event::Stdout);
+    let stderr = read_lines(child.stderr.take().unwrap(), sender.clone(), Event::Stderr);
+
+    let waiter_reaped = reaped.clone();
+    thread::spawn(move || {
+        // Send all output before the exit status
+        let _ = stdout.join();
+        let _ = stderr.join();
+
+        // Wait for the exit without reaping, so the ID stays taken until kill
+        // can no longer use it
+        unsafe {
+            let mut info = mem::zeroed();
+            libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, libc::WEXITED | libc::WNOWAIT);
+        }
+
+        *waiter_reaped.lock().unwrap() = true;
+
+        let event = match child.wait() {
+            Ok(status) => Event::Exited(status.code()),
+            Err(e)     => Event::Failed(e.to_string()),
+        };
+
+        let _ = sender.send(event);
+    });
+
+    Ok((Process { group: Some((pid, reaped)) }, receiver))
+}
+
+fn read_lines<R>(reader: R, sender: mpsc::Sender<Event>, event: fn(String) -> Event) -> thread::JoinHandle<()>
+    where R: Read + Send + 'static
+{
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut line   = vec![];
+
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_)          => (),
+            }
+
+            if sender.send(event(String::from_utf8_lossy(&line).into_owned())).is_err() {
+                return;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn events(receiver: mpsc::Receiver<Event>) -> Vec<Event> {
+        let mut events = vec![];
+
+        while let Ok(event) = receiver.recv_timeout(Duration::from_secs(5)) {
+            let last = event.is_final();
+            events.push(event);
+            if last { break }
+        }
+
+        events
+    }
+
+    #[test]
+    fn output_then_exit_status() {
+        let (_, receiver) = run("echo hi; echo oops >&2; exit 3", &Config::default()).unwrap();
+        let events        = events(receiver);
+
+        assert!(events.iter().any(|e| match *e { Event::Stdout(ref s) => s == "hi\n", _ => false }));
+        assert!(events.iter().any(|e| match *e { Event::Stderr(ref s) => s == "oops\n", _ => false }));
+        assert!(match events.last() { Some(&Event::Exited(Some(3))) => true, _ => false });
+    }
+
+    #[test]
+    fn kill_stops_the_whole_group() {
+        let (process, receiver) = run("sleep 30 | cat", &Config::default()).unwrap();
+        process.kill();
+
+        assert!(match events(receiver).last() { Some(&Event::Exited(_)) => true, _ => false });
+
+        // Also once it closed its output
+        let (process, receiver) = run("exec >/dev/null 2>&1; sleep 30", &Config::default()).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        process.kill();
+
+        let started = Instant::now();
+        assert!(match events(receiver).last() { Some(&Event::Exited(_)) => true, _ => false });
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn missing_programs_fail_to_start() {
+        assert!(run("influence-no-such-program", &Config::default()).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use fuzzy;
+use provider::{Item, Provider};
+use {Activation, Model, APPLICATIONS_LABEL};
+
+pub struct Applications {
+    pub limit: usize,
+}
+
+impl Provider for Applications {
+    fn name(&self) -> &str {
+        APPLICATIONS_LABEL
+    }
+
+    fn limit(&self) -> usize {
+        self.limit
+    }
+
+    fn query(&self, model: &Model, query: &str) -> Result<Vec<Item>, String> {
+        Ok(model.applications.iter()
+            .filter_map(|app| {
+                let fields = Some(&app.name).into_iter()
+                    .chain(app.generic_name.iter())
+                    .chain(app.keywords.iter());
+
+                fuzzy::best_score(query, fields).map(|score| {
+                    Item::new(app.name.clone(), score, Activation::run(app.command(), app.terminal))
+                })
+            })
+            .collect())
+    }
+}
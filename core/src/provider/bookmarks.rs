@@ -0,0 +1,31 @@
+use bookmark::Entry;
+use fuzzy;
+use provider::{Item, Provider};
+use {Activation, Model, BOOKMARKS_LABEL};
+
+pub struct Bookmarks;
+
+impl Provider for Bookmarks {
+    fn name(&self) -> &str {
+        BOOKMARKS_LABEL
+    }
+
+    // Bookmarks go at the top of the results, without a header
+    fn header(&self) -> Option<&str> {
+        None
+    }
+
+    fn query(&self, model: &Model, query: &str) -> Result<Vec<Item>, String> {
+        Ok(model.bookmarks.iter()
+            .filter_map(|entry| match *entry {
+                Entry::Bookmark(ref bookmark) => Some(bookmark),
+                _                             => None,
+            })
+            .filter_map(|b| {
+                fuzzy::best_score(query, b.search_fields()).map(|score| {
+                    Item::new(b.title().to_string(), score, Activation::run(b.command_line(), b.terminal))
+                })
+            })
+            .collect())
+    }
+}
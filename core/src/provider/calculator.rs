@@ -0,0 +1,21 @@
+use calc;
+use provider::{Item, Provider};
+use {Activation, Model, CALCULATOR_LABEL};
+
+pub struct Calculator;
+
+impl Provider for Calculator {
+    fn name(&self) -> &str {
+        CALCULATOR_LABEL
+    }
+
+    fn query(&self, _model: &Model, query: &str) -> Result<Vec<Item>, String> {
+        Ok(calc::eval_query(query)
+            .map(|value| {
+                let value = calc::format(value);
+                Item::new(format!("= {}", value), 0, Activation::Copy(value))
+            })
+            .into_iter()
+            .collect())
+    }
+}
@@ -0,0 +1,43 @@
+use files;
+use fuzzy;
+use provider::{Item, Provider};
+use {Activation, Model, FILES_LABEL};
+
+/// Paths for queries starting with `/`, `~` or `./`, and the indexed files
+/// for other queries
+pub struct Files {
+    pub limit: usize,
+}
+
+impl Provider for Files {
+    fn name(&self) -> &str {
+        FILES_LABEL
+    }
+
+    fn limit(&self) -> usize {
+        self.limit
+    }
+
+    fn query(&self, model: &Model, query: &str) -> Result<Vec<Item>, String> {
+        if files::is_path(query) {
+            let typed = files::split(query).1;
+
+            return Ok(files::list(query).into_iter()
+                .filter_map(|entry| fuzzy::score(typed, &entry.name).map(|score| item(entry.path, score)))
+                .collect());
+        }
+
+        Ok(model.files.iter()
+            .filter_map(|path| fuzzy::score(query, files::name(path)).map(|score| item(path.clone(), score)))
+            .collect())
+    }
+}
+
+fn item(path: String, score: i64) -> Item {
+    let icon = if path.ends_with('/') { "folder" } else { "text-x-generic" };
+
+    Item {
+        icon: Some(icon.to_string()),
+        ..Item::new(path.clone(), score, Activation::Open(path))
+    }
+}
@@ -0,0 +1,31 @@
+use fuzzy;
+use history;
+use provider::{Item, Provider};
+use {Activation, Model, HISTORY_LABEL};
+
+pub struct History {
+    pub limit: usize,
+}
+
+impl Provider for History {
+    fn name(&self) -> &str {
+        HISTORY_LABEL
+    }
+
+    fn limit(&self) -> usize {
+        self.limit
+    }
+
+    // Frequently and recently used commands get a bonus on top of the match score
+    fn query(&self, model: &Model, query: &str) -> Result<Vec<Item>, String> {
+        let now = history::now();
+
+        Ok(model.history.iter()
+            .filter_map(|e| {
+                fuzzy::score(query, &e.command).map(|score| {
+                    Item::new(e.command.clone(), score + e.rank_bonus(now), Activation::run(e.command.clone(), e.terminal))
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,24 @@
+use fuzzy;
+use provider::{Item, Provider};
+use {Activation, Model, ITEMS_LABEL};
+
+/// Items read from stdin in dmenu mode
+pub struct Items;
+
+impl Provider for Items {
+    fn name(&self) -> &str {
+        ITEMS_LABEL
+    }
+
+    fn header(&self) -> Option<&str> {
+        None
+    }
+
+    fn query(&self, model: &Model, query: &str) -> Result<Vec<Item>, String> {
+        Ok(model.items.iter()
+            .filter_map(|i| {
+                fuzzy::score(query, i).map(|score| Item::new(i.clone(), score, Activation::Run(i.clone())))
+            })
+            .collect())
+    }
+}
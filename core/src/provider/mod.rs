@@ -0,0 +1,221 @@
+//! Sources of results for the Results page
+//!
+//! Each provider gets its own section on the Results page. The page asks
+//! every registered provider for items matching the query, sorts them by
+//! score and shows at most `limit()` of them.
+
+use config::Config;
+use std::sync::mpsc;
+use std::thread;
+use {Activation, Model};
+
+pub mod applications;
+pub mod bookmarks;
+pub mod calculator;
+pub mod files;
+pub mod history;
+pub mod items;
+pub mod programs;
+pub mod script;
+
+#[derive(Clone)]
+pub struct Item {
+    /// Text shown in the row
+    pub label:      String,
+
+    /// Dimmed text shown below the label
+    pub subtitle:   Option<String>,
+
+    /// Name of an icon from the icon theme
+    pub icon:       Option<String>,
+
+    /// Rank within the section, higher is better
+    pub score:      i64,
+
+    pub activation: Activation,
+}
+
+impl Item {
+    pub fn new(label: String, score: i64, activation: Activation) -> Item {
+        Item { label, score, activation, subtitle: None, icon: None }
+    }
+}
+
+/// A query that can be run on another thread, see `Provider::query_task`
+pub type QueryTask = Box<Fn() -> Result<Vec<Item>, String> + Send>;
+
+pub trait Provider {
+    fn name(&self) -> &str;
+
+    /// Title of the section header, or None to show the items without one
+    fn header(&self) -> Option<&str> {
+        Some(self.name())
+    }
+
+    /// Maximum number of items to show
+    fn limit(&self) -> usize {
+        usize::MAX
+    }
+
+    /// Whether the provider also gets a page of its own, showing all items
+    fn page(&self) -> bool {
+        false
+    }
+
+    /// Items matching the query in any order, or an error to show instead
+    fn query(&self, model: &Model, query: &str) -> Result<Vec<Item>, String>;
+
+    /// The same query as something to run on another thread, for providers
+    /// that may be slow to answer
+    fn query_task(&self, _model: &Model, _query: &str) -> Option<QueryTask> {
+        None
+    }
+
+    /// What to do when the item is chosen
+    fn activate(&self, item: &Item) -> Activation {
+        item.activation.clone()
+    }
+}
+
+/// The providers of the launcher, in the order of their sections
+pub fn launcher(config: &Config) -> Vec<Box<Provider>> {
+    let mut providers: Vec<Box<Provider>> = vec![
+        Box::new(calculator::Calculator),
+        Box::new(files::Files { limit: config.limits.files }),
+        Box::new(bookmarks::Bookmarks),
+        Box::new(applications::Applications { limit: config.limits.applications }),
+        Box::new(history::History           { limit: config.limits.history }),
+        Box::new(programs::Programs         { limit: config.limits.programs }),
+    ];
+
+    for (index, script) in config.scripts.iter().enumerate() {
+        providers.push(Box::new(script::Script {
+            index,
+            name:  script.name.clone(),
+            limit: script.limit,
+            page:  script.page,
+        }));
+    }
+
+    providers
+}
+
+/// The providers of dmenu mode
+pub fn dmenu() -> Vec<Box<Provider>> {
+    vec![Box::new(items::Items)]
+}
+
+/// Ask the provider for items matching the query, best match first
+///
+/// Items with the same score are ordered by the length of their label, so
+/// that shorter and likely closer matches come first.
+pub fn search(provider: &Provider, model: &Model, query: &str) -> Result<Vec<Item>, String> {
+    provider.query(model, query).map(|mut items| {
+        sort(&mut items);
+        items
+    })
+}
+
+fn sort(items: &mut [Item]) {
+    items.sort_by(|a, b| b.score.cmp(&a.score).then(a.label.len().cmp(&b.label.len())));
+}
+
+/// Ask the provider for items on another thread, if it's one that may be
+/// slow to answer
+///
+/// The items come through the returned channel, best match first, the same
+/// as from `search`.
+pub fn search_in_background(provider: &Provider, model: &Model, query: &str) -> Option<mpsc::Receiver<Result<Vec<Item>, String>>> {
+    let task = provider.query_task(model, query)?;
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let _ = sender.send(task().map(|mut items| {
+            sort(&mut items);
+            items
+        }));
+    });
+
+    Some(receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::ScriptConfig;
+    use history::HistoryEntry;
+
+    fn labels(items: Vec<Item>) -> Vec<String> {
+        items.into_iter().map(|item| item.label).collect()
+    }
+
+    #[test]
+    fn search_sorts_by_score_then_length() {
+        let model = Model::dmenu(Config::default(), vec![
+            "firefox-developer-edition".to_string(),
+            "gimp".to_string(),
+            "firefox".to_string(),
+        ]);
+
+        let items = search(&items::Items, &model, "fire").unwrap();
+        assert_eq!(labels(items), vec!["firefox", "firefox-developer-edition"]);
+    }
+
+    #[test]
+    fn history_ranks_frequent_commands_higher() {
+        let now   = ::history::now();
+        let entry = |command: &str, count| HistoryEntry { command: command.to_string(), count, last_used: now, terminal: false };
+
+        let model = Model {
+            history: vec![entry("make test", 1), entry("make", 40)],
+            ..Default::default()
+        };
+
+        let items = search(&history::History { limit: 5 }, &model, "make").unwrap();
+        assert_eq!(labels(items), vec!["make", "make test"]);
+    }
+
+    #[test]
+    fn calculator_copies_the_result() {
+        let items = search(&calculator::Calculator, &Model::default(), "2 * 21").unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].label, "= 42");
+        assert_eq!(items[0].activation, Activation::Copy("42".to_string()));
+    }
+
+    #[test]
+    fn indexed_files_match_by_name() {
+        let model = Model {
+            files: vec!["~/src/main.rs".to_string(), "~/src/".to_string(), "~/main/notes.txt".to_string()],
+            ..Default::default()
+        };
+
+        let items = search(&files::Files { limit: 5 }, &model, "main").unwrap();
+        assert_eq!(labels(items), vec!["~/src/main.rs"]);
+
+        let items = search(&files::Files { limit: 5 }, &model, "src").unwrap();
+        assert_eq!(items[0].activation, Activation::Open("~/src/".to_string()));
+    }
+
+    #[test]
+    fn only_slow_providers_search_in_background() {
+        assert!(search_in_background(&calculator::Calculator, &Model::default(), "1 + 1").is_none());
+
+        let mut model = Model::default();
+        model.config.scripts.push(ScriptConfig { path: "/nonexistent/script".to_string(), ..Default::default() });
+
+        let provider = script::Script { index: 0, name: "Script".to_string(), limit: 5, page: false };
+        let receiver = search_in_background(&provider, &model, "query").unwrap();
+        assert!(receiver.recv().unwrap().is_err());
+    }
+
+    #[test]
+    fn launcher_has_a_provider_per_script() {
+        let mut config = Config::default();
+        assert_eq!(launcher(&config).len(), 6);
+
+        config.scripts.push(Default::default());
+        assert_eq!(launcher(&config).len(), 7);
+    }
+}
@@ -0,0 +1,25 @@
+use fuzzy;
+use provider::{Item, Provider};
+use {Activation, Model, PROGRAMS_LABEL};
+
+pub struct Programs {
+    pub limit: usize,
+}
+
+impl Provider for Programs {
+    fn name(&self) -> &str {
+        PROGRAMS_LABEL
+    }
+
+    fn limit(&self) -> usize {
+        self.limit
+    }
+
+    fn query(&self, model: &Model, query: &str) -> Result<Vec<Item>, String> {
+        Ok(model.programs.iter()
+            .filter_map(|p| {
+                fuzzy::score(query, p).map(|score| Item::new(p.clone(), score, Activation::Run(p.clone())))
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,58 @@
+use provider::{Item, Provider, QueryTask};
+use script;
+use {Activation, Model};
+
+/// Items printed by an external script
+pub struct Script {
+    /// Index of the script in the config
+    pub index: usize,
+    pub name:  String,
+    pub limit: usize,
+    pub page:  bool,
+}
+
+impl Provider for Script {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn limit(&self) -> usize {
+        self.limit
+    }
+
+    fn page(&self) -> bool {
+        self.page
+    }
+
+    // Scripts do their own matching, so their order is kept
+    fn query(&self, model: &Model, query: &str) -> Result<Vec<Item>, String> {
+        items(self.index, script::query(&model.config.scripts[self.index], query)?)
+    }
+
+    // Scripts can take up to their timeout to answer
+    fn query_task(&self, model: &Model, query: &str) -> Option<QueryTask> {
+        let index  = self.index;
+        let script = model.config.scripts[index].clone();
+        let query  = query.to_string();
+
+        Some(Box::new(move || items(index, script::query(&script, &query)?)))
+    }
+}
+
+fn items(index: usize, items: Vec<script::ScriptItem>) -> Result<Vec<Item>, String> {
+    Ok(items.into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let activation = match item.command {
+                Some(ref command) => Activation::Run(command.clone()),
+                None              => Activation::Select(index, item.value().to_string()),
+            };
+
+            Item {
+                subtitle: item.subtitle.clone(),
+                icon:     item.icon.clone(),
+                ..Item::new(item.label.clone(), -(i as i64), activation)
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,258 @@
+//! Running external scripts that provide results
+//!
+//! A script is called with the query, either as its only argument or on
+//! stdin, and prints one item per line. In the `lines` format every line is
+//! tab-separated:
+//!
+//! ```text
+//! label<TAB>subtitle<TAB>icon<TAB>command
+//! ```
+//!
+//! where everything but the label is optional. In the `json` format every
+//! line is an object with the same fields, plus an optional `data`:
+//!
+//! ```text
+//! {"label": "Suspend", "icon": "system-suspend", "data": "suspend"}
+//! ```
+//!
+//! Choosing an item with a command runs that command. Choosing an item without
+//! one calls the script a second time, with the item's data (or its label)
+//! instead of the query. The environment variable `INFLUENCE_STAGE` tells the
+//! two calls apart: it's `query` for the first and `select` for the second.
+
+use config::{self, ScriptConfig, ScriptFormat, ScriptInput};
+use serde_json;
+use std::io::prelude::*;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// An item printed by a script
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptItem {
+    pub label:    String,
+    #[serde(default)]
+    pub subtitle: Option<String>,
+    #[serde(default)]
+    pub icon:     Option<String>,
+
+    /// Command to run when the item is chosen, instead of calling the script
+    #[serde(default)]
+    pub command:  Option<String>,
+
+    /// Text sent back to the script when the item is chosen, instead of the label
+    #[serde(default)]
+    pub data:     Option<String>,
+}
+
+impl ScriptItem {
+    /// The text sent back to the script when the item is chosen
+    pub fn value(&self) -> &str {
+        self.data.as_ref().unwrap_or(&self.label)
+    }
+}
+
+/// Ask the script for items matching the query
+pub fn query(script: &ScriptConfig, query: &str) -> Result<Vec<ScriptItem>, String> {
+    let output = run(script, "query", query)?;
+
+    match script.format {
+        ScriptFormat::Lines => Ok(parse_lines(&output)),
+        ScriptFormat::Json  => parse_json(&output),
+    }
+}
+
+/// Tell the script which item was chosen
+pub fn select(script: &ScriptConfig, value: &str) -> Result<(), String> {
+    run(script, "select", value).map(|_| ())
+}
+
+/// The same as `select`, on another thread, with the result coming through
+/// the returned channel
+pub fn select_in_background(script: &ScriptConfig, value: &str) -> mpsc::Receiver<Result<(), String>> {
+    let (sender, receiver) = mpsc::channel();
+    let script = script.clone();
+    let value  = value.to_string();
+
+    thread::spawn(move || {
+        let _ = sender.send(select(&script, &value));
+    });
+
+    receiver
+}
+
+fn parse_lines(output: &str) -> Vec<ScriptItem> {
+    let field = |s: Option<&str>| s.and_then(|s| if s == "" { None } else { Some(s.to_string()) });
+
+    output.lines()
+        .filter(|line| line.trim() != "")
+        .map(|line| {
+            let mut fields = line.split('\t');
+
+            ScriptItem {
+                label:    fields.next().unwrap_or("").to_string(),
+                subtitle: field(fields.next()),
+                icon:     field(fields.next()),
+                command:  field(fields.next()),
+                data:     None,
+            }
+        })
+        .collect()
+}
+
+fn parse_json(output: &str) -> Result<Vec<ScriptItem>, String> {
+    output.lines()
+        .enumerate()
+        .filter(|&(_, line)| line.trim() != "")
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+/// Run the script with the given input, returning its output
+///
+/// The script is killed if it doesn't exit within its timeout. A non-zero
+/// exit status is an error, with the first line of stderr as the message.
+fn run(script: &ScriptConfig, stage: &str, input: &str) -> Result<String, String> {
+    let mut command = Command::new(config::expand_tilde(&script.path));
+    command
+        .env("INFLUENCE_STAGE", stage)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if script.input == ScriptInput::Argv {
+        command.arg(input);
+    }
+
+    let mut child = command.spawn()
+        .map_err(|e| format!("unable to run {}: {}", script.path, e))?;
+
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        if script.input == ScriptInput::Stdin {
+            // Scripts that exit without reading their input are fine
+            let _ = writeln!(stdin, "{}", input);
+        }
+    }
+
+    let stdout = read_in_background(child.stdout.take().unwrap());
+    let stderr = read_in_background(child.stderr.take().unwrap());
+
+    let timeout  = Duration::from_millis(script.timeout);
+    let deadline = Instant::now() + timeout;
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None)         => (),
+            Err(e)           => return Err(e.to_string()),
+        }
+
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("timed out after {} ms", script.timeout));
+        }
+
+        thread::sleep(Duration::from_millis(5));
+    };
+
+    // Background processes started by the script may keep the pipes open
+    let stdout = stdout.recv_timeout(timeout).unwrap_or_default();
+    let stderr = stderr.recv_timeout(timeout).unwrap_or_default();
+
+    if !status.success() {
+        return Err(stderr.lines()
+            .map(str::trim)
+            .find(|line| *line != "")
+            .map(|line| line.to_string())
+            .unwrap_or_else(|| match status.code() {
+                Some(code) => format!("exited with status {}", code),
+                None       => "killed by a signal".to_string(),
+            }));
+    }
+
+    Ok(stdout)
+}
+
+fn read_in_background<R: Read + Send + 'static>(mut reader: R) -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut bytes = vec![];
+        let _ = reader.read_to_end(&mut bytes);
+        let _ = sender.send(String::from_utf8_lossy(&bytes).into_owned());
+    });
+
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process;
+
+    #[test]
+    fn parses_lines_with_optional_fields() {
+        let items = parse_lines("Suspend\tSleep now\tsystem-suspend\tsystemctl suspend\n\nLock\n");
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].subtitle.as_ref().unwrap(), "Sleep now");
+        assert_eq!(items[0].command.as_ref().unwrap(), "systemctl suspend");
+        assert_eq!(items[1].label, "Lock");
+        assert!(items[1].icon.is_none());
+        assert_eq!(items[1].value(), "Lock");
+    }
+
+    #[test]
+    fn parses_json_lines() {
+        let items = parse_json("{\"label\": \"Suspend\", \"data\": \"suspend\"}\n\n{\"label\": \"Lock\"}").unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].value(), "suspend");
+
+        let e = parse_json("{\"label\": \"Lock\"}\n{\"lable\": \"Oops\"}").err().unwrap();
+        assert!(e.starts_with("line 2: "), "{}", e);
+    }
+
+    fn script(name: &str, source: &str, input: ScriptInput, timeout: u64) -> ScriptConfig {
+        let path = env::temp_dir().join(format!("influence-test-{}-{}.sh", name, process::id()));
+        fs::write(&path, format!("#!/bin/sh\n{}\n", source)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        ScriptConfig {
+            name:  name.to_string(),
+            path:  path.to_string_lossy().into_owned(),
+            input,
+            timeout,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn passes_the_query_and_stage() {
+        let argv  = script("argv", "echo \"$INFLUENCE_STAGE $1\"", ScriptInput::Argv, 2000);
+        let stdin = script("stdin", "read q; echo \"$INFLUENCE_STAGE $q\"", ScriptInput::Stdin, 2000);
+
+        assert_eq!(query(&argv, "foo").unwrap()[0].label, "query foo");
+        assert_eq!(query(&stdin, "bar").unwrap()[0].label, "query bar");
+        assert!(select(&argv, "foo").is_ok());
+    }
+
+    #[test]
+    fn reports_failures() {
+        let failing = script("failing", "echo >&2; echo 'no network' >&2; exit 3", ScriptInput::Argv, 2000);
+        let silent  = script("silent", "exit 3", ScriptInput::Argv, 2000);
+        let slow    = script("slow", "sleep 5", ScriptInput::Argv, 100);
+
+        assert_eq!(query(&failing, "").err().unwrap(), "no network");
+        assert_eq!(query(&silent, "").err().unwrap(), "exited with status 3");
+        assert_eq!(query(&slow, "").err().unwrap(), "timed out after 100 ms");
+        assert_eq!(select_in_background(&failing, "").recv().unwrap().err().unwrap(), "no network");
+    }
+}
@@ -0,0 +1,458 @@
+//! Files in the config and state directories, and the programs in `$PATH`
+
+use bookmark::{self, Entry};
+use history::{self, HistoryEntry};
+use libc;
+use std::env;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::fs;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::process;
+
+pub enum FileStore {
+    Bookmarks,
+    History,
+    Config,
+    Style,
+    Theme(String),
+    Tabs,
+}
+
+/// The directories influence keeps its files in
+#[derive(Debug, PartialEq)]
+pub struct Dirs {
+    /// Files the user edits: config.toml, bookmarks.txt, style.css and themes
+    pub config: PathBuf,
+
+    /// Files influence keeps for itself: history.txt and tabs.txt
+    pub state:  PathBuf,
+}
+
+impl FileStore {
+    fn path_in(&self, dirs: &Dirs) -> PathBuf {
+        match *self {
+            FileStore::Bookmarks       => dirs.config.join("bookmarks.txt"),
+            FileStore::History         => dirs.state.join("history.txt"),
+            FileStore::Config          => dirs.config.join("config.toml"),
+            FileStore::Style           => dirs.config.join("style.css"),
+            FileStore::Theme(ref name) => dirs.config.join("themes").join(format!("{}.css", name)),
+            FileStore::Tabs            => dirs.state.join("tabs.txt"),
+        }
+    }
+}
+
+/// Find the directories from the environment
+///
+/// `$INFLUENCE_CONFIG` puts every file in that one directory, which is handy
+/// for testing and for keeping separate profiles. Otherwise the XDG base
+/// directories are used: `$XDG_CONFIG_HOME/influence` and
+/// `$XDG_STATE_HOME/influence`, which default to `~/.config/influence` and
+/// `~/.local/state/influence`.
+pub fn dirs() -> Result<Dirs, Box<Error>> {
+    Ok(resolve_dirs(|name| env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from))?)
+}
+
+fn resolve_dirs<F: Fn(&str) -> Option<PathBuf>>(var: F) -> Result<Dirs, String> {
+    if let Some(dir) = var("INFLUENCE_CONFIG") {
+        return Ok(Dirs { config: dir.clone(), state: dir });
+    }
+
+    // Relative paths are invalid according to the spec and should be ignored
+    let base = |name: &str, default: &str| var(name)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| var("HOME").map(|home| home.join(default)))
+        .map(|dir| dir.join("influence"))
+        .ok_or_else(|| format!("neither ${} nor $HOME is set", name));
+
+    Ok(Dirs {
+        config: base("XDG_CONFIG_HOME", ".config")?,
+        state:  base("XDG_STATE_HOME", ".local/state")?,
+    })
+}
+
+/// Location of one of influence's files
+pub fn store_path(store: FileStore) -> Result<PathBuf, Box<Error>> {
+    Ok(store.path_in(&dirs()?))
+}
+
+/// Move the files older versions kept in `~/.config/influence` to where they
+/// belong now, when the XDG directories point elsewhere
+///
+/// Files that already exist in the new location are left alone. Returns the
+/// old and new path of every file that was moved.
+pub fn migrate() -> Result<Vec<(PathBuf, PathBuf)>, Box<Error>> {
+    if env::var_os("INFLUENCE_CONFIG").map_or(false, |v| !v.is_empty()) {
+        return Ok(vec![]);
+    }
+
+    let legacy = match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".config/influence"),
+        None       => return Ok(vec![]),
+    };
+
+    Ok(migrate_from(&legacy, &dirs()?)?)
+}
+
+fn migrate_from(legacy: &Path, dirs: &Dirs) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let files = [
+        ("bookmarks.txt", FileStore::Bookmarks.path_in(dirs)),
+        ("history.txt",   FileStore::History.path_in(dirs)),
+        ("config.toml",   FileStore::Config.path_in(dirs)),
+        ("style.css",     FileStore::Style.path_in(dirs)),
+        ("tabs.txt",      FileStore::Tabs.path_in(dirs)),
+        ("themes",        dirs.config.join("themes")),
+    ];
+
+    let mut moved = vec![];
+
+    for &(name, ref new) in files.iter() {
+        let old = legacy.join(name);
+
+        if old == *new || fs::symlink_metadata(&old).is_err() || fs::symlink_metadata(new).is_ok() {
+            continue;
+        }
+
+        if let Some(dir) = new.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        move_path(&old, new)?;
+        moved.push((old, new.clone()));
+    }
+
+    Ok(moved)
+}
+
+/// Rename a file or a directory of files, copying it when the new location is
+/// on another filesystem
+fn move_path(old: &Path, new: &Path) -> io::Result<()> {
+    match fs::rename(old, new) {
+        Err(ref e) if e.raw_os_error() == Some(libc::EXDEV) => (),
+        result => return result,
+    }
+
+    if old.is_dir() {
+        fs::create_dir(new)?;
+        for entry in fs::read_dir(old)? {
+            let entry = entry?;
+            move_path(&entry.path(), &new.join(entry.file_name()))?;
+        }
+        fs::remove_dir(old)
+    } else {
+        fs::copy(old, new)?;
+        fs::remove_file(old)
+    }
+}
+
+/// Replace one of the files with a list of lines
+pub fn write_file_list(store: FileStore, list: &[String]) -> Result<(), Box<Error>> {
+    Ok(write_lines(&store_path(store)?, list)?)
+}
+
+/// Read a list of commands from a file
+pub fn read_file_list(store: FileStore) -> Result<Vec<String>, Box<Error>> {
+    Ok(read_lines(&store_path(store)?)?)
+}
+
+/// Change the history on disk, merged with what other instances wrote
+///
+/// The history is locked while it's read, changed and written back, so
+/// instances running at the same time don't lose each other's launches.
+/// Returns the history as it is on disk now.
+pub fn update_history<F>(change: F) -> Result<Vec<HistoryEntry>, Box<Error>>
+    where F: FnOnce(&mut Vec<HistoryEntry>)
+{
+    Ok(update_history_at(&store_path(FileStore::History)?, history::now(), change)?)
+}
+
+fn update_history_at<F>(path: &Path, now: u64, change: F) -> io::Result<Vec<HistoryEntry>>
+    where F: FnOnce(&mut Vec<HistoryEntry>)
+{
+    let _lock = Lock::acquire(path)?;
+
+    let mut entries = match read_lines(path) {
+        Ok(lines)                                        => history::parse(lines, now),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => vec![],
+        Err(e)                                           => return Err(e),
+    };
+
+    change(&mut entries);
+    write_lines(path, &history::serialize(&entries))?;
+
+    Ok(entries)
+}
+
+/// An advisory lock on a file, held until it's dropped
+///
+/// The lock is taken on a separate `.name.lock` file, because writing a file
+/// replaces it with a new one.
+struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Wait until no other instance holds the lock and take it
+    fn acquire(path: &Path) -> io::Result<Lock> {
+        let (dir, name) = split_path(path)?;
+        fs::create_dir_all(dir)?;
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(format!(".{}.lock", name)))?;
+
+        loop {
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+                return Ok(Lock { _file: file });
+            }
+
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+    }
+}
+
+/// Replace a file with the lines, without ever leaving it half-written
+///
+/// The lines are written to a temporary file next to it, which is synced to
+/// disk and then renamed over the old file.
+fn write_lines(path: &Path, list: &[String]) -> io::Result<()> {
+    let (dir, name) = split_path(path)?;
+    fs::create_dir_all(dir)?;
+
+    let temp = dir.join(format!(".{}.{}.tmp", name, process::id()));
+
+    if let Err(e) = write_temp(&temp, list).and_then(|_| fs::rename(&temp, path)) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    // Make sure the rename itself survives a crash
+    File::open(dir)?.sync_all()
+}
+
+fn write_temp(path: &Path, list: &[String]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    for line in list.iter() {
+        writer.write_all(line.as_bytes())?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()?;
+    writer.get_ref().sync_all()
+}
+
+fn read_lines(path: &Path) -> io::Result<Vec<String>> {
+    let mut string = String::new();
+    let mut file = File::open(path)?;
+    let _ = file.read_to_string(&mut string)?;
+    let lines = string
+        .lines()
+        .map(|l| l.to_string())
+        .collect::<Vec<String>>();
+
+    Ok(lines)
+}
+
+/// The directory and file name of a path, with `.` for a bare file name
+fn split_path(path: &Path) -> io::Result<(&Path, String)> {
+    let name = path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a file", path.display())))?;
+
+    let dir = match path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _                                 => Path::new("."),
+    };
+
+    Ok((dir, name.to_string_lossy().into_owned()))
+}
+
+pub fn load_bookmarks() -> Vec<Entry> {
+    read_file_list(FileStore::Bookmarks)
+        .map(bookmark::parse)
+        .unwrap_or_else(|e| {
+            eprintln!("unable to read bookmarks: {}", e);
+            Default::default()
+        })
+}
+
+pub fn load_history() -> Vec<HistoryEntry> {
+    read_file_list(FileStore::History)
+        .map(|lines| history::parse(lines, history::now()))
+        .unwrap_or_else(|e| {
+            eprintln!("unable to read history: {}", e);
+            Default::default()
+        })
+}
+
+/// Get all executables in the user's PATH
+pub fn get_path_commands() -> Vec<String> {
+    path_commands(&env::var("PATH").unwrap_or_default())
+}
+
+/// Get all executables in a colon-separated list of directories, sorted and
+/// without duplicates
+fn path_commands(path: &str) -> Vec<String> {
+    let mut commands = path
+        .split(':')
+        .map(fs::read_dir)
+        .flat_map(Result::ok)
+        .flat_map(|entries| entries.flat_map(Result::ok))
+        .filter(|file|
+            file.metadata().ok()
+                .map_or(false, |meta| meta.permissions().mode() & 0o111 > 0))
+        .flat_map(|file| file.file_name().into_string())
+        .collect::<Vec<_>>();
+
+    commands.sort();
+    commands.dedup();
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("influence-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn lines_round_trip() {
+        let dir   = temp_dir("lines");
+        let path  = dir.join("new/list.txt");
+        let lines = vec!["firefox".to_string(), "".to_string(), "st -e htop".to_string()];
+
+        write_lines(&path, &lines).unwrap();
+        assert_eq!(read_lines(&path).unwrap(), lines);
+
+        // Replacing the file leaves no temporary files behind
+        write_lines(&path, &lines[..1]).unwrap();
+        assert_eq!(read_lines(&path).unwrap(), vec!["firefox"]);
+        assert_eq!(fs::read_dir(dir.join("new")).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn vars(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<PathBuf> {
+        move |name| vars.iter().find(|&&(n, _)| n == name).map(|&(_, v)| PathBuf::from(v))
+    }
+
+    #[test]
+    fn dirs_follow_xdg() {
+        assert_eq!(resolve_dirs(vars(&[("HOME", "/home/me")])), Ok(Dirs {
+            config: PathBuf::from("/home/me/.config/influence"),
+            state:  PathBuf::from("/home/me/.local/state/influence"),
+        }));
+
+        assert_eq!(resolve_dirs(vars(&[("XDG_CONFIG_HOME", "/cfg"), ("XDG_STATE_HOME", "/state")])), Ok(Dirs {
+            config: PathBuf::from("/cfg/influence"),
+            state:  PathBuf::from("/state/influence"),
+        }));
+
+        // Relative paths are ignored
+        assert_eq!(resolve_dirs(vars(&[("HOME", "/home/me"), ("XDG_CONFIG_HOME", "cfg")])).unwrap().config,
+                   PathBuf::from("/home/me/.config/influence"));
+
+        assert!(resolve_dirs(vars(&[("XDG_CONFIG_HOME", "/cfg")])).is_err());
+    }
+
+    #[test]
+    fn influence_config_overrides_everything() {
+        let dirs = resolve_dirs(vars(&[("INFLUENCE_CONFIG", "profile"), ("XDG_CONFIG_HOME", "/cfg")])).unwrap();
+
+        assert_eq!(FileStore::Config.path_in(&dirs),  PathBuf::from("profile/config.toml"));
+        assert_eq!(FileStore::History.path_in(&dirs), PathBuf::from("profile/history.txt"));
+    }
+
+    #[test]
+    fn migrate_moves_legacy_files() {
+        let dir    = temp_dir("migrate");
+        let legacy = dir.join("legacy");
+        let dirs   = Dirs { config: legacy.clone(), state: dir.join("state") };
+
+        fs::create_dir_all(legacy.join("themes")).unwrap();
+        fs::create_dir_all(&dirs.state).unwrap();
+        write_lines(&legacy.join("history.txt"), &["old".to_string()]).unwrap();
+        write_lines(&legacy.join("tabs.txt"), &["Bookmarks".to_string()]).unwrap();
+        write_lines(&dirs.state.join("tabs.txt"), &["History".to_string()]).unwrap();
+        write_lines(&legacy.join("bookmarks.txt"), &["htop".to_string()]).unwrap();
+
+        let moved = migrate_from(&legacy, &dirs).unwrap();
+
+        // The config dir is the same, so only the history moves, and the new
+        // tab order is kept
+        assert_eq!(moved, vec![(legacy.join("history.txt"), dirs.state.join("history.txt"))]);
+        assert_eq!(read_lines(&dirs.state.join("history.txt")).unwrap(), vec!["old"]);
+        assert_eq!(read_lines(&dirs.state.join("tabs.txt")).unwrap(), vec!["History"]);
+        assert!(legacy.join("bookmarks.txt").exists());
+
+        let config = Dirs { config: dir.join("config"), state: dirs.state.clone() };
+        migrate_from(&legacy, &config).unwrap();
+
+        assert_eq!(read_lines(&config.config.join("bookmarks.txt")).unwrap(), vec!["htop"]);
+        assert!(config.config.join("themes").is_dir());
+        assert!(!legacy.join("themes").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn history_updates_are_merged() {
+        let dir  = temp_dir("history");
+        let path = Arc::new(dir.join("history.txt"));
+
+        // Each thread stands in for an instance recording its own launches
+        let threads = (0..4).map(|i| {
+            let path = path.clone();
+            thread::spawn(move || for _ in 0..10 {
+                update_history_at(&path, 1000, |e| history::record(e, &format!("cmd{}", i), false, 1000, 100)).unwrap();
+            })
+        }).collect::<Vec<_>>();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let entries = history::parse(read_lines(&path).unwrap(), 1000);
+        assert_eq!(entries.len(), 4);
+        assert!(entries.iter().all(|e| e.count == 10));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn path_commands_are_executable_sorted_and_unique() {
+        let dir = temp_dir("path");
+        let a   = dir.join("a");
+        let b   = dir.join("b");
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+
+        for &(dir, name, mode) in &[(&a, "zsh", 0o755), (&a, "notes.txt", 0o644), (&b, "bash", 0o755), (&b, "zsh", 0o700)] {
+            let file = dir.join(name);
+            File::create(&file).unwrap();
+            fs::set_permissions(&file, fs::Permissions::from_mode(mode)).unwrap();
+        }
+
+        let path = format!("{}:{}:/nonexistent", a.display(), b.display());
+        assert_eq!(path_commands(&path), vec!["bash", "zsh"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
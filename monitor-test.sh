@@ -0,0 +1,72 @@
+#!/bin/bash
+
+set -e
+
+cargo build
+program=$PWD/target/debug/influence
+
+TMPDIR=/tmp/influence-monitor-test
+mkdir -p $TMPDIR/config
+rm -f $TMPDIR/*.png # not recursive! we're not valve!
+
+failed=0
+
+main() {
+    # Two monitors side by side: 0 at +0+0, 1 at +1920+0
+    Xvfb :1 +xinerama -screen 0 1920x1080x24 -screen 1 1280x1024x24 &
+    sleep 0.4s
+
+                  #  monitor    anchor          pointer    expected geometry
+    check_window     primary    bottom-left     -          500x250+40+790
+    check_window     1          bottom-right    -          500x250+2660+734
+    check_window     1          top-bar         -          1200x250+1960+40
+    check_window     pointer    center          2500,500   500x250+2310+387
+    check_window     pointer    top             100,100    500x250+710+40
+    # No window manager sets _NET_ACTIVE_WINDOW here, so this falls back to the primary monitor
+    check_window     focused    top-left        -          500x250+40+40
+    check_window     5          top-left        -          500x250+40+40
+
+    kill %1
+
+    exit $failed
+}
+
+check_window() {
+    monitor=$1
+    anchor=$2
+    pointer=$3
+    expected=$4
+
+    case $monitor in
+        [0-9]*) monitor_value=$monitor ;;
+        *)      monitor_value="\"$monitor\"" ;;
+    esac
+
+    cat > $TMPDIR/config/config.toml <<CONFIG
+[window]
+monitor = $monitor_value
+anchor  = "$anchor"
+CONFIG
+
+    if [[ $pointer != - ]]; then
+        DISPLAY=:1 xdotool mousemove ${pointer/,/ }
+    fi
+
+    DISPLAY=:1 GDK_SCALE=1 GDK_DPI_SCALE=1.0 $program --config-dir $TMPDIR/config &
+
+    sleep 0.4s
+    geom="$(DISPLAY=:1 xwininfo -root -tree | grep influence | grep -oP '\d+x\d+\+\d+\+\d+' | head -n1)"
+    DISPLAY=:1 maim -g "$geom" $TMPDIR/monitor-$monitor-$anchor.png || true
+
+    if [[ $geom == $expected ]]; then
+        echo "ok      $monitor $anchor: $geom"
+    else
+        echo "FAILED  $monitor $anchor: expected $expected, got $geom"
+        failed=1
+    fi
+
+    kill %2
+    sleep 0.5s
+}
+
+main
@@ -0,0 +1,60 @@
+//! Command line argument parsing
+
+pub const USAGE: &str = "\
+Usage: influence [OPTIONS]
+
+Options:
+    --dmenu              Read items from stdin and print the chosen one to stdout
+    -p PROMPT            Prompt shown in the entry in dmenu mode
+    -i                   Ignored, matching is always case-insensitive
+    --daemon             Stay resident in the background, hidden until shown
+    --toggle             Show the daemon's window, or hide it if it's visible
+    --page NAME          Open on the page with this name, or the start of it
+    --exit               Stop a running daemon
+    --purge-history PAT  Remove the commands matching PAT from the history and
+                         exit, with * and ? as wildcards
+    --config-dir DIR     Keep the config, bookmarks and history in DIR, like
+                         setting $INFLUENCE_CONFIG
+    -h, --help           Show this help
+
+Without --daemon or --dmenu, influence shows the window of a running daemon
+if there is one, and starts a new instance otherwise.";
+
+#[derive(Default)]
+pub struct Args {
+    pub help:   bool,
+    pub dmenu:  bool,
+    pub prompt: Option<String>,
+    pub daemon: bool,
+    pub toggle: bool,
+    pub page:   Option<String>,
+    pub exit:   bool,
+    pub config: Option<String>,
+    pub purge:  Option<String>,
+}
+
+pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+    let mut parsed = Args::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help"   => parsed.help  = true,
+            "--dmenu"         => parsed.dmenu = true,
+            "-i"              => (),
+            "-p"              => parsed.prompt = Some(args.next().ok_or("-p requires an argument")?),
+            "--daemon"        => parsed.daemon = true,
+            "--toggle"        => parsed.toggle = true,
+            "--page"          => parsed.page   = Some(args.next().ok_or("--page requires an argument")?),
+            "--exit"          => parsed.exit   = true,
+            "--purge-history" => parsed.purge  = Some(args.next().ok_or("--purge-history requires an argument")?),
+            "--config-dir"    => parsed.config = Some(args.next().ok_or("--config-dir requires an argument")?),
+            _                 => return Err(format!("unknown argument: {}", arg)),
+        }
+    }
+
+    if parsed.dmenu && parsed.daemon {
+        return Err("--dmenu and --daemon can't be combined".to_string());
+    }
+
+    Ok(parsed)
+}
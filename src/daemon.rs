@@ -0,0 +1,120 @@
+//! Resident mode: a hidden window controlled over a Unix socket
+//!
+//! Clients connect to the socket and write a single request per line, e.g.
+//! `show`, `toggle` or `page History`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::BufReader;
+use std::io::prelude::*;
+use std::io;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+
+pub enum Request {
+    /// Show the window
+    Show,
+
+    /// Show the window if it's hidden, hide it otherwise
+    Toggle,
+
+    /// Show the window on the page with this name
+    Page(String),
+
+    /// Stop the daemon
+    Exit,
+}
+
+impl Request {
+    fn parse(line: &str) -> Option<Request> {
+        let mut parts = line.trim().splitn(2, ' ');
+
+        match (parts.next(), parts.next()) {
+            (Some("show"),   None)       => Some(Request::Show),
+            (Some("toggle"), None)       => Some(Request::Toggle),
+            (Some("page"),   Some(name)) => Some(Request::Page(name.to_string())),
+            (Some("exit"),   None)       => Some(Request::Exit),
+            _                            => None,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        match *self {
+            Request::Show        => "show\n".to_string(),
+            Request::Toggle      => "toggle\n".to_string(),
+            Request::Page(ref n) => format!("page {}\n", n),
+            Request::Exit        => "exit\n".to_string(),
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/influence.sock`, or a per-user file in the temp dir
+///
+/// With `$INFLUENCE_CONFIG` set the name includes a hash of the directory, so
+/// every profile has a daemon of its own.
+pub fn socket_path() -> PathBuf {
+    let name = match env::var_os("INFLUENCE_CONFIG") {
+        Some(ref dir) if !dir.is_empty() => {
+            let mut hasher = DefaultHasher::new();
+            dir.hash(&mut hasher);
+            format!("influence-{:x}", hasher.finish())
+        },
+        _ => "influence".to_string(),
+    };
+
+    match env::var("XDG_RUNTIME_DIR") {
+        Ok(ref dir) if dir != "" => PathBuf::from(dir).join(format!("{}.sock", name)),
+        _ => {
+            let user = env::var("USER").unwrap_or_default();
+            env::temp_dir().join(format!("{}-{}.sock", name, user))
+        },
+    }
+}
+
+/// Send a request to a running daemon
+pub fn send(request: &Request) -> io::Result<()> {
+    let mut stream = UnixStream::connect(socket_path())?;
+    stream.write_all(request.to_line().as_bytes())
+}
+
+/// Start listening for requests on the socket
+///
+/// Requests are read on a separate thread and handed over through the
+/// returned channel, to be picked up by the GTK main loop.
+pub fn listen() -> io::Result<mpsc::Receiver<Request>> {
+    let path = socket_path();
+
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, "another daemon is already running"));
+        }
+
+        // Left behind by a daemon that didn't shut down cleanly
+        fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flat_map(Result::ok) {
+            for line in BufReader::new(stream).lines().flat_map(Result::ok) {
+                match Request::parse(&line) {
+                    Some(request) => if sender.send(request).is_err() { return },
+                    None          => eprintln!("invalid request: {}", line),
+                }
+            }
+        }
+    });
+
+    Ok(receiver)
+}
+
+/// Remove the socket when the daemon stops
+pub fn cleanup() {
+    let _ = fs::remove_file(socket_path());
+}
@@ -1,11 +1,16 @@
 use gtk;
 use gtk::prelude::*;
-use {CommandSource, Context, Msg, RunOptions};
+use keys::{Action, Scope};
+use {BookmarkEdit, CommandSource, Context, Mode, Msg, RunOptions};
 
 pub fn init_command_entry(context: &Context) -> gtk::Entry {
     let command_entry = gtk::Entry::new();
     command_entry.set_size_request(-1, (context.res_scale)(30));
 
+    if let Mode::Dmenu { prompt: Some(ref prompt), .. } = context.model.mode {
+        command_entry.set_placeholder_text(prompt.as_str());
+    }
+
     connect!(
         context.relm,
         command_entry,
@@ -13,42 +18,39 @@ pub fn init_command_entry(context: &Context) -> gtk::Entry {
         Some(Msg::CommandInputChanged(widget.get_text().unwrap_or_default()))
     );
 
+    let keys = context.model.keys.clone();
     connect!(
         context.relm,
         command_entry,
-        connect_key_press_event(_, ev),
+        connect_key_press_event(entry, ev),
         return {
-            use gdk::enums::key;
-            use gdk::ModifierType;
-
-            let state      = ev.get_state();
-            let ctrl_held  = state.contains(ModifierType::CONTROL_MASK);
-            let shift_held = state.contains(ModifierType::SHIFT_MASK);
-            let alt_held   = state.contains(ModifierType::MOD1_MASK);
+            let run = |source, quit, record| (Some(Msg::RunCommandFromSource(source, RunOptions {
+                quit,
+                record,
+                terminal: false,
+            })), Inhibit(true));
 
-            match ev.get_keyval() {
+            match keys.action(ev, &[Scope::Entry]) {
                 // Move through list
-                key::Up   => (Some(Msg::MoveListSelection(-1)), Inhibit(true)),
-                key::Down => (Some(Msg::MoveListSelection( 1)), Inhibit(true)),
-                // key::Down => (Some(Msg::ShiftFocus(FocusTarget::Notebook)), Inhibit(true)),
-                // key::Up   => (Some(Msg::MoveListSelection(ev.clone())), Inhibit(true)),
-                // key::Down => (Some(Msg::MoveListSelection(ev.clone())), Inhibit(true)),
-
-                // Run the command
-                key::Return => (Some(Msg::RunCommandFromSource(
-                            if shift_held {
-                                CommandSource::Entry
-                            } else {
-                                CommandSource::ListSelection(true)
-                            },
-                            RunOptions {
-                                quit:   !ctrl_held,
-                                record: !alt_held,
-                            }
-                            )), Inhibit(true)),
+                Some(Action::SelectPrev) => (Some(Msg::MoveListSelection(-1)), Inhibit(true)),
+                Some(Action::SelectNext) => (Some(Msg::MoveListSelection( 1)), Inhibit(true)),
+
+                // Run the selection, or the text in the entry if nothing is selected
+                Some(Action::Run)         => run(CommandSource::ListSelection(true), true,  true),
+                Some(Action::RunKeepOpen) => run(CommandSource::ListSelection(true), false, true),
+                Some(Action::RunNoRecord) => run(CommandSource::ListSelection(true), true,  false),
+                Some(Action::RunEntry)    => run(CommandSource::Entry,               true,  true),
 
                 // Fill entry with selected bookmark
-                key::Tab => (Some(Msg::CompleteEntry), Inhibit(true)),
+                Some(Action::Complete) => (Some(Msg::CompleteEntry), Inhibit(true)),
+
+                Some(Action::DeleteWord) => {
+                    entry.emit_delete_from_cursor(gtk::DeleteType::WordEnds, -1);
+                    (None, Inhibit(true))
+                },
+
+                // Bookmark the text in the entry
+                Some(Action::Bookmark) => (Some(Msg::EditBookmarks(BookmarkEdit::Add(CommandSource::Entry))), Inhibit(true)),
 
                 _ => (None, Inhibit(false)),
             }
@@ -57,3 +59,136 @@ pub fn init_command_entry(context: &Context) -> gtk::Entry {
 
     command_entry
 }
+
+/// Unselectable row with a separator and a title, used to divide lists into sections
+pub fn header_row(title: &str) -> gtk::ListBoxRow {
+    let row   = gtk::ListBoxRow::new();
+    let box_  = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    let sep   = gtk::Separator::new(gtk::Orientation::Horizontal);
+    let label = gtk::Label::new(Some(title));
+    label.set_halign(gtk::Align::Start);
+    label.set_size_request(-1, 25);
+    row.set_sensitive(false);
+    row.set_can_focus(false);
+    box_.add(&sep);
+    box_.add(&label);
+    row.add(&box_);
+    row.get_style_context().map(|ctx| ctx.add_class("header"));
+    row
+}
+
+/// Icon, title and dimmed subtitle of a row, each but the title optional
+///
+/// The characters of the title at the given positions are highlighted.
+pub fn item_row(icon: Option<&str>, title: &str, subtitle: Option<&str>, highlight: &[usize]) -> gtk::Box {
+    let box_ = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+
+    if let Some(icon) = icon {
+        let image = gtk::Image::new_from_icon_name(icon, gtk::IconSize::LargeToolbar.into());
+        image.get_style_context().map(|ctx| ctx.add_class("icon"));
+        box_.add(&image);
+    }
+
+    let labels = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    let label  = gtk::Label::new(Some(title));
+    label.set_halign(gtk::Align::Start);
+    labels.add(&label);
+
+    if !highlight.is_empty() {
+        highlight_label(&label, title, highlight);
+    }
+
+    if let Some(subtitle) = subtitle {
+        let subtitle = gtk::Label::new(Some(subtitle));
+        subtitle.set_halign(gtk::Align::Start);
+        subtitle.get_style_context().map(|ctx| ctx.add_class("subtitle"));
+        label.get_style_context().map(|ctx| ctx.add_class("title"));
+        labels.add(&subtitle);
+    }
+
+    box_.add(&labels);
+    box_
+}
+
+/// Set the text of a label with the characters at the given positions highlighted
+///
+/// Pango markup can't be styled with CSS classes, so the colour is taken from
+/// `@define-color highlight_color` in the CSS instead.
+pub fn highlight_label(label: &gtk::Label, text: &str, positions: &[usize]) {
+    let color = lookup_color(label, "highlight_color")
+        .map(|c| format!(" foreground=\"{}\"", c))
+        .unwrap_or_default();
+
+    label.set_markup(&highlight_markup(text, positions, &format!("<span weight=\"bold\"{}>", color)));
+}
+
+/// A colour from `@define-color` in the CSS, as `#rrggbb`
+pub fn lookup_color<W: IsA<gtk::Widget>>(widget: &W, name: &str) -> Option<String> {
+    widget.get_style_context()
+        .and_then(|ctx| ctx.lookup_color(name))
+        .map(|c| format!("#{:02x}{:02x}{:02x}", (c.red * 255.0) as u8, (c.green * 255.0) as u8, (c.blue * 255.0) as u8))
+}
+
+/// Escape text for Pango markup, wrapping runs of characters at the given
+/// positions in the opening tag and `</span>`
+fn highlight_markup(text: &str, positions: &[usize], open_tag: &str) -> String {
+    let mut markup = String::new();
+    let mut open   = false;
+
+    for (i, c) in text.chars().enumerate() {
+        let highlighted = positions.contains(&i);
+
+        if highlighted && !open {
+            markup.push_str(open_tag);
+        } else if !highlighted && open {
+            markup.push_str("</span>");
+        }
+
+        open = highlighted;
+
+        match c {
+            '&'  => markup.push_str("&amp;"),
+            '<'  => markup.push_str("&lt;"),
+            '>'  => markup.push_str("&gt;"),
+            '\'' => markup.push_str("&apos;"),
+            '"'  => markup.push_str("&quot;"),
+            c    => markup.push(c),
+        }
+    }
+
+    if open {
+        markup.push_str("</span>");
+    }
+
+    markup
+}
+
+/// Unselectable row with an error message, shown in place of a provider's items
+pub fn error_row(message: &str) -> gtk::ListBoxRow {
+    let row   = gtk::ListBoxRow::new();
+    let label = gtk::Label::new(Some(message));
+    label.set_halign(gtk::Align::Start);
+    label.set_line_wrap(true);
+    row.set_can_focus(false);
+    row.set_selectable(false);
+    row.set_activatable(false);
+    row.add(&label);
+    row.get_style_context().map(|ctx| ctx.add_class("error"));
+    row
+}
+
+/// Select the first focussable row at or after the index, or before it if there is none
+pub fn select_nearest_row(listbox: &gtk::ListBox, index: i32, grab_focus: bool) {
+    let rows = listbox.get_children();
+    let row  = rows.iter().skip(index as usize).find(|row| row.get_can_focus())
+        .or_else(|| rows.iter().take(index as usize).rev().find(|row| row.get_can_focus()))
+        .and_then(|row| row.clone().downcast::<gtk::ListBoxRow>().ok());
+
+    if let Some(row) = row {
+        listbox.select_row(Some(&row));
+        listbox.set_focus_child(&row);
+        if grab_focus {
+            row.grab_focus();
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Key bindings: every action that has a key, and the chords bound to them
+//!
+//! Chords are written as modifiers and a GDK key name joined with `+`, such as
+//! `ctrl+j`, `alt+Up` or `ctrl+shift+Return`. The `[keys]` table of the config
+//! replaces the chords of the actions it names and keeps the defaults of the
+//! others:
+//!
+//! ```toml
+//! [keys]
+//! select-next = ["Down", "ctrl+j"]
+//! delete-word = []
+//! ```
+
+use gdk;
+use gdk::ModifierType;
+use std::collections::BTreeMap;
+
+/// Something a key can do
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Quit,
+    RunInTerminal,
+    RunWithOutput,
+    OpenFolder,
+    NextPage,
+    PrevPage,
+    Page(i32),
+    Run,
+    RunEntry,
+    RunKeepOpen,
+    RunNoRecord,
+    Complete,
+    DeleteWord,
+    SelectNext,
+    SelectPrev,
+    FocusEntry,
+    Bookmark,
+    DeleteHistory,
+    DeleteBookmark,
+    InsertSeparator,
+    DeleteSeparator,
+    MoveBookmarkUp,
+    MoveBookmarkDown,
+    RerunOutput,
+}
+
+/// Where a key press happened
+#[derive(Clone, Copy, PartialEq)]
+pub enum Scope {
+    Window, // anywhere, before the focused widget sees the key
+    Entry,
+    List,   // any of the lists
+    History,
+    Bookmarks,
+    Output,
+}
+
+/// Every action with its name in the config and its default chords
+const ACTIONS: &[(Action, &str, &[&str])] = &[
+    (Action::Quit,             "quit",               &["Escape"]),
+    (Action::RunInTerminal,    "run-in-terminal",    &["ctrl+shift+Return"]),
+    (Action::RunWithOutput,    "run-with-output",    &["ctrl+o"]),
+    (Action::OpenFolder,       "open-folder",        &["ctrl+shift+o"]),
+    (Action::NextPage,         "next-page",          &["alt+Down"]),
+    (Action::PrevPage,         "prev-page",          &["alt+Up"]),
+    (Action::Page(0),          "page-1",             &["alt+1"]),
+    (Action::Page(1),          "page-2",             &["alt+2"]),
+    (Action::Page(2),          "page-3",             &["alt+3"]),
+    (Action::Page(3),          "page-4",             &["alt+4"]),
+    (Action::Page(4),          "page-5",             &["alt+5"]),
+    (Action::Page(5),          "page-6",             &["alt+6"]),
+    (Action::Page(6),          "page-7",             &["alt+7"]),
+    (Action::Page(7),          "page-8",             &["alt+8"]),
+    (Action::Page(8),          "page-9",             &["alt+9"]),
+    (Action::Page(9),          "page-10",            &["alt+0"]),
+    (Action::Run,              "run",                &["Return"]),
+    (Action::RunEntry,         "run-entry",          &["shift+Return"]),
+    (Action::RunKeepOpen,      "run-keep-open",      &["ctrl+Return"]),
+    (Action::RunNoRecord,      "run-no-record",      &["alt+Return"]),
+    (Action::Complete,         "complete",           &["Tab"]),
+    (Action::DeleteWord,       "delete-word",        &["ctrl+w"]),
+    (Action::SelectNext,       "select-next",        &["Down", "ctrl+j", "ctrl+n"]),
+    (Action::SelectPrev,       "select-prev",        &["Up", "ctrl+k", "ctrl+p"]),
+    (Action::FocusEntry,       "focus-entry",        &["Tab"]),
+    (Action::Bookmark,         "bookmark",           &["ctrl+b"]),
+    (Action::DeleteHistory,    "delete-history",     &["shift+Delete"]),
+    (Action::DeleteBookmark,   "delete-bookmark",    &["shift+Delete"]),
+    (Action::InsertSeparator,  "insert-separator",   &["Insert"]),
+    (Action::DeleteSeparator,  "delete-separator",   &["ctrl+Delete"]),
+    (Action::MoveBookmarkUp,   "move-bookmark-up",   &["ctrl+Up"]),
+    (Action::MoveBookmarkDown, "move-bookmark-down", &["ctrl+Down"]),
+    (Action::RerunOutput,      "rerun-output",       &["ctrl+r"]),
+];
+
+impl Action {
+    /// Where the action's keys are handled
+    fn scopes(&self) -> &'static [Scope] {
+        use self::Action::*;
+
+        match *self {
+            Quit | RunInTerminal | RunWithOutput | OpenFolder |
+            NextPage | PrevPage | Page(_) => &[Scope::Window],
+            Run | RunEntry | RunKeepOpen | RunNoRecord | Complete | DeleteWord  => &[Scope::Entry],
+            SelectNext | SelectPrev => &[Scope::Entry, Scope::List],
+            FocusEntry              => &[Scope::List, Scope::Output],
+            Bookmark                => &[Scope::Entry, Scope::History],
+            DeleteHistory           => &[Scope::History],
+            DeleteBookmark | InsertSeparator | DeleteSeparator |
+            MoveBookmarkUp | MoveBookmarkDown => &[Scope::Bookmarks],
+            RerunOutput             => &[Scope::Output],
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        ACTIONS.iter().find(|&&(action, _, _)| action == *self).unwrap().1
+    }
+}
+
+impl Scope {
+    /// Whether a key press can be in both scopes at once
+    fn overlaps(self, other: Scope) -> bool {
+        match (self, other) {
+            (Scope::Window, _) | (_, Scope::Window) => true,
+            (Scope::List, Scope::History) | (Scope::History, Scope::List) => true,
+            (Scope::List, Scope::Bookmarks) | (Scope::Bookmarks, Scope::List) => true,
+            (a, b) => a == b,
+        }
+    }
+}
+
+fn default_chords(defaults: &[&str]) -> Vec<Chord> {
+    defaults.iter().map(|s| Chord::parse(s).expect("invalid default key binding")).collect()
+}
+
+/// The modifiers that take part in chords, others like num lock are ignored
+fn chord_modifiers() -> ModifierType {
+    ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK | ModifierType::MOD1_MASK | ModifierType::SUPER_MASK
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Chord {
+    key:       u32,
+    modifiers: ModifierType,
+}
+
+impl Chord {
+    fn parse(chord: &str) -> Result<Chord, String> {
+        let mut parts     = chord.split('+').collect::<Vec<_>>();
+        let name          = parts.pop().unwrap_or("");
+        let mut modifiers = ModifierType::empty();
+
+        for part in parts {
+            modifiers |= match part.to_lowercase().as_str() {
+                "ctrl" | "control" => ModifierType::CONTROL_MASK,
+                "shift"            => ModifierType::SHIFT_MASK,
+                "alt"              => ModifierType::MOD1_MASK,
+                "super"            => ModifierType::SUPER_MASK,
+                _                  => return Err(format!("unknown modifier {:?} in {:?}", part, chord)),
+            };
+        }
+
+        let key = gdk::keyval_from_name(name);
+        if key == 0 || key == 0xffffff {
+            return Err(format!("unknown key {:?} in {:?}", name, chord));
+        }
+
+        Ok(Chord { key: gdk::keyval_to_lower(key), modifiers })
+    }
+
+    fn from_event(ev: &gdk::EventKey) -> Chord {
+        Chord {
+            key:       gdk::keyval_to_lower(ev.get_keyval()),
+            modifiers: ev.get_state() & chord_modifiers(),
+        }
+    }
+}
+
+pub struct Keymap {
+    bindings: Vec<(Chord, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let (keymap, errors) = Keymap::new(&BTreeMap::new());
+        assert!(errors.is_empty(), "default key bindings conflict");
+        keymap
+    }
+}
+
+impl Keymap {
+    /// Bind the default chords, replaced by the ones from the config
+    ///
+    /// Actions with unknown keys, or with chords also bound to an action that
+    /// can be used in the same place, keep their defaults. The problems are
+    /// returned along with the keymap.
+    pub fn new(config: &BTreeMap<String, Vec<String>>) -> (Keymap, Vec<String>) {
+        let mut errors = vec![];
+
+        for name in config.keys() {
+            if !ACTIONS.iter().any(|&(_, n, _)| n == name) {
+                errors.push(format!("unknown action {:?}", name));
+            }
+        }
+
+        // The chords of each action, and whether they came from the config
+        let mut actions = ACTIONS.iter()
+            .map(|&(action, name, defaults)| {
+                let chords = config.get(name)
+                    .map(|chords| chords.iter().map(|s| Chord::parse(s)).collect::<Result<Vec<_>, _>>());
+
+                match chords {
+                    Some(Ok(chords)) => (action, chords, true),
+                    Some(Err(e))     => {
+                        errors.push(e);
+                        (action, default_chords(defaults), false)
+                    },
+                    None => (action, default_chords(defaults), false),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // The defaults don't conflict, so this ends once every action that
+        // takes part in a conflict is back to them
+        loop {
+            let bindings = actions.iter()
+                .flat_map(|&(action, ref chords, _)| chords.iter().map(move |&chord| (chord, action)))
+                .collect::<Vec<_>>();
+
+            let conflict = bindings.iter().enumerate().filter_map(|(i, &(chord, action))| {
+                bindings[..i].iter()
+                    .find(|&&(c, a)| c == chord && a != action &&
+                        action.scopes().iter().any(|&s| a.scopes().iter().any(|&t| s.overlaps(t))))
+                    .map(|&(_, other)| (other, action))
+            }).next();
+
+            let (first, second) = match conflict {
+                Some(conflict) => conflict,
+                None           => return (Keymap { bindings }, errors),
+            };
+
+            errors.push(format!("{} and {} are bound to the same key", first.name(), second.name()));
+
+            for &mut (action, ref mut chords, ref mut configured) in &mut actions {
+                if *configured && (action == first || action == second) {
+                    let defaults = ACTIONS.iter().find(|&&(a, _, _)| a == action).unwrap().2;
+                    *chords     = default_chords(defaults);
+                    *configured = false;
+                }
+            }
+        }
+    }
+
+    /// The action bound to the key press in any of the scopes
+    pub fn action(&self, ev: &gdk::EventKey, scopes: &[Scope]) -> Option<Action> {
+        let chord = Chord::from_event(ev);
+
+        self.bindings.iter()
+            .find(|&&(c, action)| c == chord && action.scopes().iter().any(|s| scopes.contains(s)))
+            .map(|&(_, action)| action)
+    }
+}
@@ -1,22 +1,23 @@
-/// Make key::{Up, Down} events skip separators in ListBoxes
+/// Move the selection in a ListBox up (-1) or down (1), skipping separators and headers
 macro_rules! listbox_skip_separators {
-    ($listbox:ident, $k:ident) => {{
-        let dir = match $k {
-            key::Up   => -1,
-            key::Down =>  1,
-            _ => unreachable!()
-        };
+    ($listbox:ident, $dir:expr) => {{
+        let dir: i32 = $dir;
+
+        // Distance to the next focussable row, if there is one
+        let distance = match $listbox.get_selected_row() {
+            Some(row) => {
+                let mut i = row.get_index() + dir;
+                while $listbox.get_row_at_index(i).map_or(false, |next| !next.get_can_focus()) {
+                    i += dir;
+                }
 
-        let next_row_is_separator = $listbox.get_selected_row()
-            .map(     |row|  row.get_index() + dir)
-            .and_then(|i|    $listbox.get_row_at_index(i))
-            .map(     |next| !next.get_can_focus())
-            .unwrap_or(false);
+                $listbox.get_row_at_index(i).map(|_| (i - row.get_index()) * dir)
+            },
+            None => Some(1),
+        };
 
-        if next_row_is_separator {
-            $listbox.emit_move_cursor(MovementStep::DisplayLines, dir * 2);
-        } else {
-            $listbox.emit_move_cursor(MovementStep::DisplayLines, dir);
+        if let Some(distance) = distance {
+            $listbox.emit_move_cursor(MovementStep::DisplayLines, dir * distance);
         }
 
         (None, Inhibit(true))
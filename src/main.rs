@@ -1,45 +1,47 @@
 #![feature(nll)]
 
-extern crate itertools;
 extern crate gdk;
 extern crate gtk;
+extern crate influence_core;
+extern crate inotify;
 #[macro_use]
 extern crate relm;
 #[macro_use]
 extern crate relm_derive;
 
 use gdk::prelude::*;
-use gtk::Orientation;
 use gtk::Window;
 use gtk::prelude::*;
-use itertools::Itertools;
+use influence_core::bookmark::Entry;
+use influence_core::config::Config;
+use influence_core::provider::{self, Item, Provider};
+use influence_core::store::{self, FileStore};
+use influence_core::{files, fuzzy, history, launch, output, script, Activation};
+use influence_core::{APPLICATIONS_LABEL, BOOKMARKS_LABEL, HISTORY_LABEL, ITEMS_LABEL, OUTPUT_LABEL, RESULTS_LABEL};
 use relm::{Relm, Update, Widget};
+use std::cell::Cell;
 use std::env;
-use std::fs::File;
-use std::fs;
-use std::io::BufWriter;
 use std::io::prelude::*;
-use std::os::unix::fs::PermissionsExt;
+use std::io;
 use std::path::PathBuf;
-use std::process::Command;
+use std::process;
+use std::rc::Rc;
+use std::sync::mpsc;
+use daemon::Request;
+use keys::{Action, Keymap, Scope};
+use watch::Change;
 
 #[macro_use]
 mod macros;
 
+mod cli;
+mod daemon;
 mod gui;
+mod keys;
 mod page;
-
-const BOOKMARKS_LABEL: &str = "Bookmarks";
-const HISTORY_LABEL:   &str = "History";
-const RESULTS_LABEL:   &str = "Results";
-const PROGRAMS_LABEL:  &str = "Programs";
-
-const HISTORY_MAXLEN: usize = 50;
-
-pub enum FileStore {
-    Bookmarks,
-    History,
-}
+mod placement;
+mod theme;
+mod watch;
 
 // Used during gui initialization
 pub struct Context<'a> {
@@ -48,30 +50,77 @@ pub struct Context<'a> {
     relm:      &'a Relm<Win>,
 }
 
+pub enum Mode {
+    /// Run commands and record them in the history
+    Launcher,
+
+    /// Print the chosen item to stdout, like dmenu
+    Dmenu {
+        prompt: Option<String>,
+    },
+}
+
+/// Everything needed to start the window
+pub struct Options {
+    mode:     Mode,
+    data:     influence_core::Model,
+
+    /// Requests from clients when running as a daemon
+    requests: Option<mpsc::Receiver<Request>>,
+
+    /// Name of the page to show first
+    page:     Option<String>,
+
+    /// Problems found before the window existed, such as a broken config
+    errors:   Vec<String>,
+}
+
 pub struct Model {
-    bookmarks:          Vec<String>,
-    history:            Vec<String>,
-    programs:           Vec<String>,
+    mode:               Mode,
+    resident:           bool,
+    requests:           Option<mpsc::Receiver<Request>>,
+    initial_page:       Option<String>,
+
+    /// The config, and everything the providers search through
+    data:               influence_core::Model,
+    providers:          Vec<Box<Provider>>,
     focus_results_page: bool,
+
+    /// Shared with the key press handlers of the pages
+    keys:               Rc<Keymap>,
+
+    /// Problems to show in the status label once the window is up
+    errors:             Vec<String>,
 }
 
 #[derive(Msg)]
 pub enum Msg {
     CommandInputChanged(String),
     PageSwitched(gtk::Widget),
+    TabsReordered,
     MoveListSelection(i32),
     RemoveHistoryEntry(i32),
+    Reload(Change),
+    EditBookmarks(BookmarkEdit),
     RunCommandFromSource(CommandSource, RunOptions),
-    RunCommand(String, RunOptions),
+    RunWithOutput(CommandSource),
+    OpenFolder,
+    Output(u32, output::Event),
+    ProviderResults(u32, usize, Result<Vec<Item>, String>),
+    ScriptSelected(usize, bool, Result<(), String>),
+    RerunOutput,
+    CopyOutput,
     ShiftFocus(FocusTarget),
     SelectPage(Page),
     CompleteEntry,
+    Remote(Request),
     Quit,
 }
 
 pub enum Page {
-    Abs(i32), // page 1, page 2, ...
-    Rel(i32), // next page (1), prev page (-1)
+    Abs(i32),     // page 1, page 2, ...
+    Rel(i32),     // pages forward (1) or back (-1), wrapping around
+    Name(String), // page with this tab label, or the only one starting with it
 }
 
 pub enum FocusTarget {
@@ -81,6 +130,15 @@ pub enum FocusTarget {
 
 pub enum NotebookTab {
     ListBox(gtk::ListBox),
+    Output,
+}
+
+pub enum BookmarkEdit {
+    Add(CommandSource),   // bookmark the command, at the end of the list
+    Remove(i32),          // remove the bookmark at this row
+    Move(i32, i32),       // move the bookmark at this row up (-1) or down (1)
+    InsertSeparator(i32), // insert a separator below this row
+    RemoveSeparator(i32), // remove the separator or header below this row
 }
 
 pub enum CommandSource {
@@ -94,40 +152,81 @@ pub struct RunOptions {
 
     /// Whether to add this command to the history
     record: bool,
+
+    /// Whether to run the command in a terminal emulator
+    terminal: bool,
 }
 
 pub struct Win {
-    relm:            Relm<Win>,
-    model:           Model,
-    window:          Window,
-    history_listbox: gtk::ListBox,
-    results_listbox: gtk::ListBox,
-    command_entry:   gtk::Entry,
-    notebook:        gtk::Notebook,
-    current_tab:     gtk::Widget,
+    relm:                 Relm<Win>,
+    model:                Model,
+    window:               Window,
+    bookmarks_listbox:    gtk::ListBox,
+    history_listbox:      gtk::ListBox,
+    results_listbox:      gtk::ListBox,
+    applications_listbox: gtk::ListBox,
+    command_entry:        gtk::Entry,
+    status_label:         gtk::Label,
+    notebook:             gtk::Notebook,
+    current_tab:          gtk::Widget,
+
+    /// Provider index and item for each row in results_listbox, None for headers
+    results_rows:         Vec<Option<(usize, Item)>>,
+
+    /// The query the results are for, which run of the providers answered
+    /// it, and the items of each provider that answered so far, in provider
+    /// order
+    results_query:        String,
+    results_run:          Rc<Cell<u32>>,
+    results_sections:     Vec<(usize, Result<Vec<Item>, String>)>,
+
+    provider_pages:       Vec<ProviderPage>,
+
+    output_page:          page::output::OutputPage,
+
+    /// The command shown on the Output page, and which run of it, so output
+    /// of an earlier run can be ignored
+    output_command:       Option<String>,
+    output_run:           Rc<Cell<u32>>,
+
+    /// The process of the current run, while it may still be running
+    output_process:       Option<output::Process>,
+
+    theme:                theme::Theme,
+}
+
+/// Page showing all items of a single provider
+pub struct ProviderPage {
+    provider: usize,
+    listbox:  gtk::ListBox,
+    items:    Vec<Item>,
 }
 
 impl Update for Win {
     type Model = Model;
-    type ModelParam = ();
+    type ModelParam = Options;
     type Msg = Msg;
 
-    fn model(_relm: &Relm<Self>, _param: Self::ModelParam) -> Model {
-        let bookmarks = read_file_list(FileStore::Bookmarks).unwrap_or_else(|e| {
-            println!("unable to read bookmarks: {}", e);
-            Default::default()
-        });
+    fn model(_relm: &Relm<Self>, options: Self::ModelParam) -> Model {
+        let Options { mode, data, requests, page, mut errors } = options;
+        let resident = requests.is_some();
 
-        let history = read_file_list(FileStore::History).unwrap_or_else(|e| {
-            println!("unable to read history: {}", e);
-            Default::default()
-        });
+        let (keys, key_errors) = Keymap::new(&data.config.keys);
+        if !key_errors.is_empty() {
+            errors.push(format!("invalid key bindings, using the defaults for them: {}", key_errors.join("; ")));
+        }
 
-        let programs = get_path_commands();
+        let keys = Rc::new(keys);
+
+        let providers = match mode {
+            Mode::Launcher     => provider::launcher(&data.config),
+            Mode::Dmenu { .. } => provider::dmenu(),
+        };
 
         Model {
+            mode, resident, requests, data, providers, keys, errors,
+            initial_page:       page,
             focus_results_page: true,
-            bookmarks, history, programs,
         }
     }
 
@@ -135,14 +234,24 @@ impl Update for Win {
         match event {
             Msg::CommandInputChanged(s)          => self.command_input_changed(s),
             Msg::PageSwitched(page)              => self.page_switched(page),
+            Msg::TabsReordered                   => self.save_tab_order(),
             Msg::MoveListSelection(dir)          => self.move_list_selection(dir),
             Msg::RemoveHistoryEntry(i)           => self.remove_history_entry(i),
+            Msg::Reload(change)                  => self.reload(change),
+            Msg::EditBookmarks(edit)             => self.edit_bookmarks(edit),
             Msg::RunCommandFromSource(src, opts) => self.run_command_from_source(src, opts),
-            Msg::RunCommand(s, opts)             => self.run_command(s, opts),
+            Msg::RunWithOutput(src)              => self.run_with_output(src),
+            Msg::OpenFolder                      => self.open_folder(),
+            Msg::Output(run, event)              => self.output_event(run, event),
+            Msg::ProviderResults(run, i, items)  => self.provider_results(run, i, items),
+            Msg::ScriptSelected(i, quit, result) => self.script_selected(i, quit, result),
+            Msg::RerunOutput                     => self.rerun_output(),
+            Msg::CopyOutput                      => self.copy_output(),
             Msg::ShiftFocus(target)              => self.shift_focus(target),
             Msg::SelectPage(page)                => self.select_page(page),
             Msg::CompleteEntry                   => self.complete_entry(),
-            Msg::Quit                            => gtk::main_quit(),
+            Msg::Remote(request)                 => self.handle_request(request),
+            Msg::Quit                            => self.quit(),
         }
     }
 }
@@ -154,7 +263,7 @@ impl Widget for Win {
         self.window.clone()
     }
 
-    fn view(relm: &Relm<Self>, model: Self::Model) -> Self {
+    fn view(relm: &Relm<Self>, mut model: Self::Model) -> Self {
         // Create window
         let window = gtk::Window::new(gtk::WindowType::Toplevel);
         window.set_wmclass("influence", "influence");
@@ -165,24 +274,15 @@ impl Widget for Win {
 
         // Position window
         let screen = window.get_screen().unwrap();
-        let monitor_id = screen.get_primary_monitor();
-        let monitor = screen.get_monitor_geometry(monitor_id);
         let resolution = screen.get_property_resolution() / 96.0;
         let res_scale = |i: i32| ((i as f64) * resolution) as i32;
-
-        let padding = res_scale(40);
-        let window_width = res_scale(500);
-        let window_height = res_scale(250);
-        let window_x = monitor.x + padding;
-        let window_y = monitor.y + monitor.height - padding - window_height;
-        window.move_(window_x, window_y);
-        window.set_default_size(window_width, window_height);
+        placement::place(&window, &model.data.config.window);
         window.set_border_width(res_scale(5) as u32);
 
-        // Apply custom application CSS
-        let css_provider = gtk::CssProvider::new();
-        let _ = css_provider.load_from_data(include_bytes!("main.css"));
-        gtk::StyleContext::add_provider_for_screen(&screen, &css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+        // Apply the built-in CSS, then the user's theme and style.css on top
+        let theme = theme::Theme::new(&screen);
+        let mut errors = model.errors.split_off(0);
+        errors.extend(load_style(&theme, &model.data.config));
 
         // Context for initializing the widgets
         let context = Context {
@@ -206,57 +306,83 @@ impl Widget for Win {
             Some(Msg::PageSwitched(widget.clone()))
         );
 
-        // UI: Bookmarks
-        let bookmarks_listbox = page::bookmarks::init_page(&context);
-        let scroller = gtk::ScrolledWindow::new(None, None);
-        scroller.add(&bookmarks_listbox);
-        notebook.add(&scroller);
-        notebook.set_tab_label_text(&scroller, BOOKMARKS_LABEL);
-
-        let current_tab = bookmarks_listbox.clone().upcast();
-
-        // UI: History
-        let history_listbox = page::history::init_page(&context);
-        let scroller = gtk::ScrolledWindow::new(None, None);
-        scroller.add(&history_listbox);
-        notebook.add(&scroller);
-        notebook.set_tab_label_text(&scroller, HISTORY_LABEL);
-
-        // UI: Search results
-        let results_listbox = page::results::init_page(&context);
-        let scroller = gtk::ScrolledWindow::new(None, None);
-        scroller.add(&results_listbox);
-        notebook.add(&scroller);
-        notebook.set_tab_label_text(&scroller, RESULTS_LABEL);
+        // UI: Pages
+        let add_page = |listbox: &gtk::ListBox, title: &str| {
+            let scroller = gtk::ScrolledWindow::new(None, None);
+            scroller.add(listbox);
+            notebook.add(&scroller);
+            notebook.set_tab_label_text(&scroller, title);
+        };
+
+        let bookmarks_listbox    = page::bookmarks::init_page(&context);
+        let history_listbox      = page::history::init_page(&context);
+        let results_listbox      = page::results::init_page(&context);
+        let applications_listbox = page::applications::init_page(&context);
+        let output_page          = page::output::init_page(&context);
+
+        match model.mode {
+            Mode::Launcher => {
+                add_page(&bookmarks_listbox,    BOOKMARKS_LABEL);
+                add_page(&history_listbox,      HISTORY_LABEL);
+                add_page(&results_listbox,      RESULTS_LABEL);
+                add_page(&applications_listbox, APPLICATIONS_LABEL);
+
+                notebook.add(&output_page.container);
+                notebook.set_tab_label_text(&output_page.container, OUTPUT_LABEL);
+            },
+            Mode::Dmenu { .. } => {
+                add_page(&page::items::init_page(&context), ITEMS_LABEL);
+                add_page(&results_listbox,                  RESULTS_LABEL);
+            },
+        }
+
+        let provider_pages = model.providers.iter().enumerate()
+            .filter(|&(_, provider)| provider.page())
+            .map(|(provider, p)| {
+                let listbox = page::results::init_page(&context);
+                add_page(&listbox, p.name());
+                ProviderPage { provider, listbox, items: vec![] }
+            })
+            .collect::<Vec<_>>();
 
         // UI: Command input
         let command_entry = gui::init_command_entry(&context);
         root_container.add(&command_entry);
 
-        // Window events
+        // UI: Errors, e.g. from commands that couldn't be started
+        let status_label = gtk::Label::new(None);
+        status_label.set_halign(gtk::Align::Start);
+        status_label.set_line_wrap(true);
+        status_label.set_no_show_all(true);
+        status_label.get_style_context().map(|ctx| ctx.add_class("status"));
+        root_container.add(&status_label);
+
+        // Window events, seen before the focused widget's own handlers
+        let keys = model.keys.clone();
         connect!(
             relm,
             window,
-            connect_key_press_event(_, key),
+            connect_key_press_event(_, ev),
             return {
-                use gdk::enums::key;
                 use Page::{Abs, Rel};
-                let alt_held = key.get_state().contains(gdk::ModifierType::MOD1_MASK);
-                match key.get_keyval() {
-                    key::Escape           => (Some(Msg::Quit),                Inhibit(true)),
-                    key::_1   if alt_held => (Some(Msg::SelectPage(Abs(0))),  Inhibit(true)),
-                    key::_2   if alt_held => (Some(Msg::SelectPage(Abs(1))),  Inhibit(true)),
-                    key::_3   if alt_held => (Some(Msg::SelectPage(Abs(2))),  Inhibit(true)),
-                    key::_4   if alt_held => (Some(Msg::SelectPage(Abs(3))),  Inhibit(true)),
-                    key::_5   if alt_held => (Some(Msg::SelectPage(Abs(4))),  Inhibit(true)),
-                    key::_6   if alt_held => (Some(Msg::SelectPage(Abs(5))),  Inhibit(true)),
-                    key::_7   if alt_held => (Some(Msg::SelectPage(Abs(6))),  Inhibit(true)),
-                    key::_8   if alt_held => (Some(Msg::SelectPage(Abs(7))),  Inhibit(true)),
-                    key::_9   if alt_held => (Some(Msg::SelectPage(Abs(8))),  Inhibit(true)),
-                    key::_0   if alt_held => (Some(Msg::SelectPage(Abs(9))),  Inhibit(true)),
-                    key::Up   if alt_held => (Some(Msg::SelectPage(Rel(-1))), Inhibit(true)),
-                    key::Down if alt_held => (Some(Msg::SelectPage(Rel( 1))), Inhibit(true)),
-                    _                     => (None,                           Inhibit(false)),
+                match keys.action(ev, &[Scope::Window]) {
+                    // Run the selection in a terminal, from the entry or a list
+                    Some(Action::RunInTerminal) => (Some(Msg::RunCommandFromSource(
+                        CommandSource::ListSelection(true),
+                        RunOptions { quit: true, record: true, terminal: true },
+                    )), Inhibit(true)),
+
+                    // Run the selection and show its output on the Output page
+                    Some(Action::RunWithOutput) => (Some(Msg::RunWithOutput(CommandSource::ListSelection(true))), Inhibit(true)),
+
+                    // Open the folder containing the selected file
+                    Some(Action::OpenFolder) => (Some(Msg::OpenFolder), Inhibit(true)),
+
+                    Some(Action::Quit)     => (Some(Msg::Quit),                Inhibit(true)),
+                    Some(Action::Page(n))  => (Some(Msg::SelectPage(Abs(n))),  Inhibit(true)),
+                    Some(Action::PrevPage) => (Some(Msg::SelectPage(Rel(-1))), Inhibit(true)),
+                    Some(Action::NextPage) => (Some(Msg::SelectPage(Rel( 1))), Inhibit(true)),
+                    _                      => (None,                           Inhibit(false)),
                 }
             }
         );
@@ -265,7 +391,7 @@ impl Widget for Win {
             relm,
             window,
             connect_delete_event(_, _),
-            return (Some(Msg::Quit), Inhibit(false))
+            return (Some(Msg::Quit), Inhibit(true)) // the daemon only hides the window
         );
 
         for tab in notebook.get_children().iter() {
@@ -274,21 +400,118 @@ impl Widget for Win {
             notebook.set_tab_reorderable(tab, true);
         }
 
-        window.show_all();
+        if let Mode::Launcher = model.mode {
+            // Put the tabs back in the order they were dragged in, pages that
+            // weren't there back then stay at the end
+            let order = store::read_file_list(FileStore::Tabs).unwrap_or_default();
+            let mut position = 0;
+
+            for name in order {
+                let tab = notebook.get_children().into_iter()
+                    .find(|tab| notebook.get_tab_label_text(tab).map_or(false, |label| label == name));
+
+                if let Some(tab) = tab {
+                    notebook.reorder_child(&tab, Some(position));
+                    position += 1;
+                }
+            }
+
+            notebook.set_property_page(0);
+
+            connect!(relm, notebook, connect_page_reordered(_, _, _), Msg::TabsReordered);
+        }
+
+        let current_tab = notebook.get_nth_page(Some(0)).unwrap();
+
+        if let Some(requests) = model.requests.take() {
+            let relm = relm.clone();
+            gtk::timeout_add(50, move || {
+                while let Ok(request) = requests.try_recv() {
+                    relm.stream().emit(Msg::Remote(request));
+                }
+                gtk::Continue(true)
+            });
+        }
+
+        if let Mode::Launcher = model.mode {
+            let changes = store::dirs()
+                .map_err(|e| e.to_string())
+                .and_then(|dirs| watch::watch(&dirs.config, &dirs.state).map_err(|e| e.to_string()));
+
+            match changes {
+                Ok(changes) => {
+                    let relm = relm.clone();
+                    gtk::timeout_add(200, move || {
+                        // Editors and installs often touch files several times in a row
+                        let mut pending = vec![];
+                        while let Ok(change) = changes.try_recv() {
+                            if !pending.contains(&change) {
+                                pending.push(change);
+                            }
+                        }
+
+                        for change in pending {
+                            relm.stream().emit(Msg::Reload(change));
+                        }
+
+                        gtk::Continue(true)
+                    });
+                },
+                Err(e) => eprintln!("unable to watch for changes: {}", e),
+            }
+        }
+
+        if !provider_pages.is_empty() {
+            // Fill the provider pages before anything is typed
+            relm.stream().emit(Msg::CommandInputChanged(String::new()));
+        }
+
+        if let Some(page) = model.initial_page.take() {
+            relm.stream().emit(Msg::SelectPage(Page::Name(page)));
+        }
+
+        if model.resident {
+            // Stay hidden until a client asks for the window
+            root_container.show_all();
+        } else {
+            window.show_all();
+        }
+
         command_entry.grab_focus();
 
         let relm = relm.clone();
 
-        Win {
+        let win = Win {
             relm, model, window,
-            history_listbox, results_listbox, command_entry, notebook,
-            current_tab
+            bookmarks_listbox, history_listbox, results_listbox, applications_listbox,
+            command_entry, status_label, notebook,
+            current_tab,
+            results_rows: vec![],
+            results_query: String::new(),
+            results_run: Rc::new(Cell::new(0)),
+            results_sections: vec![],
+            provider_pages,
+            output_page,
+            output_command: None,
+            output_run: Rc::new(Cell::new(0)),
+            output_process: None,
+            theme,
+        };
+
+        if !errors.is_empty() {
+            win.show_error(&errors.join("\n"));
         }
+
+        win
     }
 }
 
 impl Win {
     fn get_current_tab(&self) -> NotebookTab {
+        if self.current_tab == self.output_page.container.clone().upcast::<gtk::Widget>() {
+            return NotebookTab::Output;
+        }
+
         let listbox = self.current_tab.clone()
             .downcast::<gtk::ScrolledWindow>().ok()
             .and_then(|s| s.get_child())
@@ -314,6 +537,7 @@ impl Win {
                     NotebookTab::ListBox(listbox) => {
                         listbox.get_focus_child().map(|w| w.grab_focus());
                     },
+                    NotebookTab::Output => self.output_page.text_view.grab_focus(),
                 }
             },
             FocusTarget::Entry => {
@@ -324,15 +548,59 @@ impl Win {
 
     fn select_page(&self, page: Page) {
         match page {
-            Page::Abs(n)  => self.notebook.set_property_page(n),
-            Page::Rel(-1) => self.notebook.prev_page(),
-            Page::Rel( 1) => self.notebook.next_page(),
-            Page::Rel(_)  => unimplemented!()
+            Page::Abs(n)     => self.notebook.set_property_page(n),
+            Page::Rel(n)     => {
+                // Wrap around at either end
+                let count   = self.notebook.get_n_pages() as i32;
+                let current = self.notebook.get_current_page().map_or(0, |page| page as i32);
+
+                if count > 0 {
+                    self.notebook.set_property_page(((current + n) % count + count) % count);
+                }
+            },
+            Page::Name(name) => {
+                let labels = self.tab_labels().iter().map(|label| label.to_lowercase()).collect::<Vec<_>>();
+                let name   = name.to_lowercase();
+
+                // An exact match, or else the only page starting with the name
+                let page = labels.iter().position(|label| *label == name).or_else(|| {
+                    let mut matches = labels.iter().enumerate().filter(|&(_, label)| label.starts_with(&name));
+                    match (matches.next(), matches.next()) {
+                        (Some((n, _)), None) => Some(n),
+                        _                    => None,
+                    }
+                });
+
+                match page {
+                    Some(n) => self.notebook.set_property_page(n as i32),
+                    None    => eprintln!("no such page: {}", name),
+                }
+            },
+        }
+    }
+
+    /// Labels of the tabs, in the order they're shown
+    fn tab_labels(&self) -> Vec<String> {
+        self.notebook.get_children().iter()
+            .map(|tab| self.notebook.get_tab_label_text(tab).unwrap_or_default())
+            .collect()
+    }
+
+    /// Remember the order the user dragged the tabs in, so alt+1 and so on
+    /// keep going to the same pages
+    fn save_tab_order(&self) {
+        if let Err(e) = store::write_file_list(FileStore::Tabs, &self.tab_labels()) {
+            eprintln!("unable to save the tab order: {}", e);
         }
     }
 
     fn command_input_changed(&mut self, s: String) {
-        if self.model.focus_results_page {
+        self.status_label.hide();
+
+        // A leading space only keeps the launch out of the history
+        let s = if self.model.data.config.history.ignore_space { s.trim_left().to_string() } else { s };
+
+        if self.model.focus_results_page && s != "" {
             self.results_listbox.get_parent()
                 .and_then(|viewport| viewport.get_parent())
                 .and_then(|scroller| self.notebook.page_num(&scroller))
@@ -341,74 +609,114 @@ impl Win {
             self.model.focus_results_page = false;
         }
 
+        let run = self.results_run.get() + 1;
+        self.results_run.set(run);
+        self.results_query = s.clone();
+        self.results_sections.clear();
+
+        // Providers with a page of their own are asked even without a query,
+        // so the page isn't empty. Slow ones answer later, see provider_results.
+        let mut pending = vec![];
+        for index in 0..self.model.providers.len() {
+            if s == "" && !self.provider_pages.iter().any(|p| p.provider == index) { continue }
+
+            let provider = self.model.providers[index].as_ref();
+            match provider::search_in_background(provider, &self.model.data, &s) {
+                Some(receiver) => pending.push((index, receiver)),
+                None           => {
+                    let items = provider::search(provider, &self.model.data, &s);
+                    self.show_section(index, items);
+                },
+            }
+        }
+
+        self.fill_results(false);
+
+        if !pending.is_empty() {
+            let current = self.results_run.clone();
+            let relm    = self.relm.clone();
+            gtk::timeout_add(20, move || {
+                // Typing on asked the providers again
+                if current.get() != run { return gtk::Continue(false) }
+
+                pending.retain(|&(index, ref receiver)| match receiver.try_recv() {
+                    Ok(items) => {
+                        relm.stream().emit(Msg::ProviderResults(run, index, items));
+                        false
+                    },
+                    Err(mpsc::TryRecvError::Empty)        => true,
+                    Err(mpsc::TryRecvError::Disconnected) => false,
+                });
+                gtk::Continue(!pending.is_empty())
+            });
+        }
+    }
+
+    /// Show the items of a provider that answered in the background, unless
+    /// the query changed in the meantime
+    fn provider_results(&mut self, run: u32, index: usize, items: Result<Vec<Item>, String>) {
+        if run != self.results_run.get() { return }
+
+        self.show_section(index, items);
+        self.fill_results(true);
+    }
+
+    /// Put a provider's items on its own page, and keep them for the Results page
+    fn show_section(&mut self, index: usize, items: Result<Vec<Item>, String>) {
+        let query = self.results_query.clone();
+
+        if let Some(page) = self.provider_pages.iter().position(|p| p.provider == index) {
+            self.fill_provider_page(page, &query, items.clone());
+        }
+
+        if query != "" {
+            self.results_sections.retain(|&(i, _)| i != index);
+
+            let at = self.results_sections.iter().position(|&(i, _)| i > index).unwrap_or(self.results_sections.len());
+            self.results_sections.insert(at, (index, items));
+        }
+    }
+
+    /// Rebuild results_listbox from results_sections, keeping the selected
+    /// item selected if asked to and it's still there
+    fn fill_results(&mut self, keep_selection: bool) {
+        let selected = if keep_selection {
+            self.results_listbox.get_selected_row()
+                .and_then(|row| self.get_row_activation(&self.results_listbox, &row))
+        } else {
+            None
+        };
+
         for row in self.results_listbox.get_children() {
             self.results_listbox.remove(&row);
         }
 
-        if s == "" { return }
+        self.results_rows.clear();
 
-        for bookmark in self.model.bookmarks.iter() {
-            if bookmark.contains(&s) {
-                let label = gtk::Label::new(Some(bookmark.as_str()));
-                label.set_halign(gtk::Align::Start);
-                self.results_listbox.add(&label);
-            }
-        }
+        if self.results_query == "" { return }
 
-        let row   = gtk::ListBoxRow::new();
-        let box_  = gtk::Box::new(Orientation::Vertical, 0);
-        let sep   = gtk::Separator::new(Orientation::Horizontal);
-        let label = gtk::Label::new(Some(HISTORY_LABEL));
-        label.set_halign(gtk::Align::Start);
-        label.set_size_request(-1, 25);
-        row.set_sensitive(false);
-        row.set_can_focus(false);
-        box_.add(&sep);
-        box_.add(&label);
-        row.add(&box_);
-        row.get_style_context().map(|ctx| ctx.add_class("header"));
-        self.results_listbox.add(&row);
-
-        let mut limiter = 0;
-        let limit = 5;
-        for entry in self.model.history.iter() {
-            if entry.contains(&s) {
-                limiter += 1;
-                if limiter > limit { break; }
-
-                let label = gtk::Label::new(Some(entry.as_str()));
-                label.set_halign(gtk::Align::Start);
-                self.results_listbox.add(&label);
-            }
+        let query = self.results_query.clone();
+        for (index, items) in self.results_sections.clone() {
+            self.add_results_section(index, &query, items);
         }
 
-        let row   = gtk::ListBoxRow::new();
-        let box_  = gtk::Box::new(Orientation::Vertical, 0);
-        let sep   = gtk::Separator::new(Orientation::Horizontal);
-        let label = gtk::Label::new(Some(PROGRAMS_LABEL));
-        label.set_halign(gtk::Align::Start);
-        label.set_size_request(-1, 25);
-        row.set_sensitive(false);
-        row.set_can_focus(false);
-        box_.add(&sep);
-        box_.add(&label);
-        row.add(&box_);
-        row.get_style_context().map(|ctx| ctx.add_class("header"));
-        self.results_listbox.add(&row);
-
-        let mut limiter = 0;
-        let limit = 10;
-        for entry in self.model.programs.iter() {
-            if entry.contains(&s) {
-                limiter += 1;
-                if limiter > limit { break; }
-
-                let label = gtk::Label::new(Some(entry.as_str()));
-                label.set_halign(gtk::Align::Start);
-                self.results_listbox.add(&label);
-            }
+        let position = selected.and_then(|selected| self.results_rows.iter().position(|row| {
+            row.as_ref().map_or(false, |&(provider, ref item)| self.model.providers[provider].activate(item) == selected)
+        }));
+
+        match position {
+            Some(position) => {
+                let row = self.results_listbox.get_row_at_index(position as i32).unwrap();
+                self.results_listbox.set_focus_child(&row);
+                self.results_listbox.select_row(Some(&row));
+            },
+            None => self.select_first_result(),
         }
 
+        self.results_listbox.show_all();
+    }
+
+    fn select_first_result(&self) {
         // Select first focussable (non-header) row
         for row in self.results_listbox.get_children() {
             if row.get_can_focus() {
@@ -417,8 +725,57 @@ impl Win {
                 break;
             }
         }
+    }
 
-        self.results_listbox.show_all();
+    /// Add a provider's items or error to results_listbox, below an optional header
+    fn add_results_section(&mut self, provider: usize, query: &str, items: Result<Vec<Item>, String>) {
+        if let Ok(ref items) = items {
+            if items.is_empty() { return }
+        }
+
+        if let Some(header) = self.model.providers[provider].header() {
+            self.results_listbox.add(&gui::header_row(header));
+            self.results_rows.push(None);
+        }
+
+        match items {
+            Ok(items) => {
+                let limit = self.model.providers[provider].limit();
+                for item in items.into_iter().take(limit) {
+                    self.results_listbox.add(&item_row(&item, query));
+                    self.results_rows.push(Some((provider, item)));
+                }
+            },
+            Err(message) => {
+                self.results_listbox.add(&gui::error_row(&message));
+                self.results_rows.push(None);
+            },
+        }
+    }
+
+    /// Replace the rows of a provider's own page
+    fn fill_provider_page(&mut self, page: usize, query: &str, items: Result<Vec<Item>, String>) {
+        let page = &mut self.provider_pages[page];
+
+        for row in page.listbox.get_children() {
+            page.listbox.remove(&row);
+        }
+
+        match items {
+            Ok(items) => {
+                for item in &items {
+                    page.listbox.add(&item_row(item, query));
+                }
+                page.items = items;
+            },
+            Err(message) => {
+                page.listbox.add(&gui::error_row(&message));
+                page.items = vec![];
+            },
+        }
+
+        gui::select_nearest_row(&page.listbox, 0, false);
+        page.listbox.show_all();
     }
 
     fn move_list_selection(&self, dir: i32) {
@@ -436,67 +793,274 @@ impl Win {
                     });
                 }
             },
+            NotebookTab::Output => (),
         }
     }
 
     fn remove_history_entry(&mut self, index: i32) {
-        self.model.history.remove(index as usize);
+        if let Err(e) = self.model.data.remove_history_entry(index as usize) {
+            eprintln!("unable to write history: {}", e);
+        }
+
+        // The history was merged with the one on disk, so other rows may
+        // have moved too
+        self.refill_history(index, true);
+    }
+
+    /// Show the history as it is in the model, selecting the row nearest to the index
+    fn refill_history(&self, index: i32, grab_focus: bool) {
+        page::history::fill(&self.history_listbox, &self.model.data.history);
+        gui::select_nearest_row(&self.history_listbox, index, grab_focus);
+    }
+
+    fn edit_bookmarks(&mut self, edit: BookmarkEdit) {
+        if let Mode::Dmenu { .. } = self.model.mode { return }
+
+        let len = self.model.data.bookmarks.len() as i32;
+
+        // Row to select afterwards, if the edit was made on the Bookmarks page
+        let selection = match edit {
+            BookmarkEdit::Add(source) => {
+                let added = match self.get_activation_from_source(&source) {
+                    Some(Activation::Run(command))      => self.model.data.add_bookmark(&command, false),
+                    Some(Activation::Terminal(command)) => self.model.data.add_bookmark(&command, true),
+                    _                                   => false,
+                };
+
+                if !added { return }
+
+                None
+            },
+            BookmarkEdit::Remove(i) if i < len => {
+                self.model.data.bookmarks.remove(i as usize);
+                Some(i)
+            },
+            BookmarkEdit::Move(i, dir) if i < len && i + dir >= 0 && i + dir < len => {
+                self.model.data.bookmarks.swap(i as usize, (i + dir) as usize);
+                Some(i + dir)
+            },
+            BookmarkEdit::InsertSeparator(i) if i < len => {
+                self.model.data.bookmarks.insert(i as usize + 1, Entry::Separator);
+                Some(i)
+            },
+            BookmarkEdit::RemoveSeparator(i) if i + 1 < len => {
+                match self.model.data.bookmarks[i as usize + 1] {
+                    Entry::Bookmark(_) => return,
+                    _                  => { self.model.data.bookmarks.remove(i as usize + 1); },
+                }
+                Some(i)
+            },
+            _ => return,
+        };
+
+        page::bookmarks::fill(&self.bookmarks_listbox, &self.model.data.bookmarks);
+
+        if let Some(index) = selection {
+            gui::select_nearest_row(&self.bookmarks_listbox, index, true);
+        }
+
+        if let Err(e) = self.model.data.save_bookmarks() {
+            eprintln!("unable to write bookmarks: {}", e);
+        }
+    }
+
+    /// Reload the theme and style.css, showing any errors in them
+    fn reload_style(&self) {
+        self.status_label.hide();
+
+        let errors = load_style(&self.theme, &self.model.data.config);
+        if !errors.is_empty() {
+            self.show_error(&errors.join("\n"));
+        }
+    }
+
+    /// Reload something that changed on disk, keeping the selection in its list
+    fn reload(&mut self, change: Change) {
+        let listbox = match change {
+            Change::Bookmarks => self.bookmarks_listbox.clone(),
+            Change::History   => self.history_listbox.clone(),
+            Change::Programs  => self.results_listbox.clone(),
+            Change::Style     => return self.reload_style(),
+        };
+
+        let selected   = listbox.get_selected_row();
+        let index      = selected.as_ref().map(|row| row.get_index());
+        let has_focus  = selected.as_ref().map_or(false, |row| row.has_focus());
+        let activation = selected.as_ref().and_then(|row| self.get_row_activation(&listbox, row));
 
-        self.history_listbox.get_row_at_index(index)
-            .map(|r| self.history_listbox.remove(&r));
+        match change {
+            Change::Bookmarks => {
+                self.model.data.bookmarks = store::load_bookmarks();
+                page::bookmarks::fill(&self.bookmarks_listbox, &self.model.data.bookmarks);
+            },
+            Change::History => {
+                self.model.data.history = store::load_history();
+                page::history::fill(&self.history_listbox, &self.model.data.history);
+            },
+            Change::Programs => self.model.data.programs = store::get_path_commands(),
+            Change::Style    => unreachable!(),
+        }
 
-        self.history_listbox.get_row_at_index(index)
-            .or_else(|| self.history_listbox.get_row_at_index(index - 1))
-            .map(|r| { self.history_listbox.select_row(&r); r.grab_focus(); });
+        // All of them have a section on the Results page
+        let text = self.command_entry.get_text().unwrap_or_default();
+        self.command_input_changed(text);
 
-        if let Err(e) = write_file_list(FileStore::History, &self.model.history) {
-            println!("unable to write history: {}", e);
+        if let Some(index) = index {
+            // Prefer the row with the same command, it might have moved
+            let same_command = listbox.get_children().iter()
+                .flat_map(|row| row.clone().downcast::<gtk::ListBoxRow>().ok())
+                .find(|row| activation.is_some() && self.get_row_activation(&listbox, row) == activation)
+                .map(|row| row.get_index());
+
+            gui::select_nearest_row(&listbox, same_command.unwrap_or(index), has_focus);
         }
     }
 
-    fn get_selected_command(&self) -> Option<String> {
+    fn get_selected_activation(&self) -> Option<Activation> {
         match self.get_current_tab() {
             NotebookTab::ListBox(listbox) => listbox
                 .get_selected_row()
-                .and_then(|row|   if row.is_visible() { Some(row) } else { None })
-                .and_then(|row|   row.get_child())
+                .and_then(|row| if row.is_visible() { Some(row) } else { None })
+                .and_then(|row| self.get_row_activation(&listbox, &row)),
+            NotebookTab::Output => None,
+        }
+    }
+
+    fn get_row_activation(&self, listbox: &gtk::ListBox, row: &gtk::ListBoxRow) -> Option<Activation> {
+        let index = row.get_index() as usize;
+
+        if *listbox == self.results_listbox {
+            return self.results_rows.get(index)
+                .and_then(|row| row.as_ref())
+                .map(|&(provider, ref item)| self.model.providers[provider].activate(item));
+        }
+
+        if let Some(page) = self.provider_pages.iter().find(|p| p.listbox == *listbox) {
+            return page.items.get(index).map(|item| self.model.providers[page.provider].activate(item));
+        }
+
+        if *listbox == self.bookmarks_listbox {
+            match self.model.data.bookmarks.get(index) {
+                Some(&Entry::Bookmark(ref b)) => Some(Activation::run(b.command_line(), b.terminal)),
+                _                             => None,
+            }
+        } else if *listbox == self.history_listbox {
+            self.model.data.history.get(index).map(|e| Activation::run(e.command.clone(), e.terminal))
+        } else if *listbox == self.applications_listbox {
+            self.model.data.applications.get(index).map(|app| Activation::run(app.command(), app.terminal))
+        } else {
+            row.get_child()
                 .and_then(|label| label.downcast::<gtk::Label>().ok())
-                .and_then(|label| label.get_text()),
+                .and_then(|label| label.get_text())
+                .map(Activation::Run)
         }
     }
 
-    fn run_command_from_source(&mut self, source: CommandSource, opts: RunOptions) {
-        match source {
+    fn get_activation_from_source(&self, source: &CommandSource) -> Option<Activation> {
+        match *source {
             CommandSource::ListSelection(or_entry) => {
-                if let Some(bookmark) = self.get_selected_command() {
-                    self.run_command(bookmark, opts);
-                } else if or_entry {
-                    self.run_command_from_source(CommandSource::Entry, opts);
-                }
+                self.get_selected_activation().or_else(|| {
+                    if or_entry {
+                        self.get_activation_from_source(&CommandSource::Entry)
+                    } else {
+                        None
+                    }
+                })
             },
-            CommandSource::Entry => {
-                if let Some(cmd) = self.command_entry.get_text() {
-                    self.run_command(cmd, opts);
-                }
+            CommandSource::Entry => self.command_entry.get_text().map(Activation::Run),
+        }
+    }
+
+    fn run_command_from_source(&mut self, source: CommandSource, opts: RunOptions) {
+        match self.get_activation_from_source(&source) {
+            Some(Activation::Run(cmd))             => self.run_command(cmd, opts),
+            Some(Activation::Terminal(cmd))        => self.run_command(cmd, RunOptions { terminal: true, ..opts }),
+            Some(Activation::Copy(text))           => self.copy_to_clipboard(text, opts),
+            Some(Activation::Select(script, text)) => self.select_script_item(script, text, opts),
+            Some(Activation::Open(path))           => self.open_path(path, opts),
+            None                                   => (),
+        }
+    }
+
+    /// Send the chosen item back to the script it came from, see script_selected
+    fn select_script_item(&mut self, script: usize, text: String, opts: RunOptions) {
+        let result = script::select_in_background(&self.model.data.config.scripts[script], &text);
+        let relm   = self.relm.clone();
+
+        gtk::timeout_add(20, move || {
+            match result.try_recv() {
+                Ok(result) => {
+                    relm.stream().emit(Msg::ScriptSelected(script, opts.quit, result));
+                    gtk::Continue(false)
+                },
+                Err(mpsc::TryRecvError::Empty)        => gtk::Continue(true),
+                Err(mpsc::TryRecvError::Disconnected) => gtk::Continue(false),
             }
+        });
+    }
+
+    /// Quit once the script handled the chosen item, if asked to
+    fn script_selected(&mut self, script: usize, quit: bool, result: Result<(), String>) {
+        if let Err(e) = result {
+            let message = format!("{}: {}", self.model.data.config.scripts[script].name, e);
+            return self.show_error(&message);
+        }
+
+        if quit {
+            self.relm.stream().emit(Msg::Quit);
+        }
+    }
+
+    fn open_path(&mut self, path: String, opts: RunOptions) {
+        if let Err(e) = launch::open(&path, &self.model.data.config) {
+            return self.show_error(&e);
+        }
+
+        if opts.quit {
+            self.relm.stream().emit(Msg::Quit);
+        }
+    }
+
+    fn open_folder(&mut self) {
+        if let Some(Activation::Open(path)) = self.get_selected_activation() {
+            self.open_path(files::parent(&path), RunOptions { quit: true, record: false, terminal: false });
+        }
+    }
+
+    fn copy_to_clipboard(&mut self, text: String, opts: RunOptions) {
+        let clipboard = gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD);
+        clipboard.set_text(&text);
+
+        // Hand the text over to the clipboard manager, if any, so it's still
+        // there after we quit
+        clipboard.store();
+
+        if opts.quit {
+            self.relm.stream().emit(Msg::Quit);
         }
     }
 
     fn run_command(&mut self, cmd: String, opts: RunOptions) {
-        let _ = Command::new("/bin/bash")
-            .arg("-c")
-            .arg(format!("{} &", cmd))
-            .spawn()
-            .expect("failed to execute child")
-            .wait();
+        if let Mode::Dmenu { .. } = self.model.mode {
+            println!("{}", cmd);
+            if opts.quit {
+                gtk::main_quit();
+            }
+            return;
+        }
+
+        let result = if opts.terminal {
+            launch::spawn(&launch::in_terminal(&cmd, &self.model.data.config), &self.model.data.config)
+        } else {
+            launch::spawn(&cmd, &self.model.data.config)
+        };
+
+        if let Err(e) = result {
+            return self.show_error(&e);
+        }
 
         if opts.record {
-            self.model.history.retain(|c| c != &cmd);
-            self.model.history.insert(0, cmd);
-            self.model.history.truncate(HISTORY_MAXLEN);
-            if let Err(e) = write_file_list(FileStore::History, &self.model.history) {
-                println!("unable to write history: {}", e);
-            }
+            self.record_history(&cmd, opts.terminal);
         }
 
         if opts.quit {
@@ -504,69 +1068,295 @@ impl Win {
         }
     }
 
-    fn complete_entry(&self) {
-        if let Some(bookmark) = self.get_selected_command() {
-            self.command_entry.set_text(&bookmark);
-            self.command_entry.set_position(bookmark.len() as i32);
+    fn record_history(&mut self, cmd: &str, terminal: bool) {
+        // A query typed with a leading space keeps whatever was found with it
+        // out of the history too
+        let query = self.command_entry.get_text().unwrap_or_default();
+        if self.model.data.config.history.ignore_space && query.starts_with(' ') { return }
+
+        if let Err(e) = self.model.data.record_history(cmd, terminal) {
+            eprintln!("unable to write history: {}", e);
         }
+
+        // Keep the History page in step when the window stays open
+        let selected = self.history_listbox.get_selected_row();
+        let index    = selected.as_ref().map_or(0, |row| row.get_index());
+        let focus    = selected.as_ref().map_or(false, |row| row.has_focus());
+        self.refill_history(index, focus);
     }
-}
 
-fn write_file_list(store: FileStore, list: &Vec<String>) -> Result<(), Box<std::error::Error>> {
-    let mut path = PathBuf::from(std::env::var("HOME")?);
+    /// Run the command and show what it prints on the Output page, keeping the window open
+    fn run_with_output(&mut self, source: CommandSource) {
+        if let Mode::Dmenu { .. } = self.model.mode { return }
+
+        let cmd = match self.get_activation_from_source(&source) {
+            Some(Activation::Run(cmd)) | Some(Activation::Terminal(cmd)) => cmd,
+            _                                                            => return,
+        };
+
+        if self.start_output(cmd.clone()) {
+            self.record_history(&cmd, false);
+        }
+
+        self.notebook.page_num(&self.output_page.container)
+            .map(|num| self.notebook.set_property_page(num as i32));
+    }
 
-    match store {
-        FileStore::Bookmarks => path.push(".config/influence/bookmarks.txt"),
-        FileStore::History   => path.push(".config/influence/history.txt"),
+    fn rerun_output(&mut self) {
+        if let Some(cmd) = self.output_command.clone() {
+            self.start_output(cmd);
+        }
     }
 
-    let file       = File::create(path)?;
-    let mut writer = BufWriter::new(file);
+    /// Run the command for the Output page, stopping the one before it
+    ///
+    /// Returns whether the command could be started.
+    fn start_output(&mut self, cmd: String) -> bool {
+        self.stop_output();
+
+        let run = self.output_run.get() + 1;
+        self.output_run.set(run);
+        self.output_page.clear(&cmd);
+        self.output_command = Some(cmd.clone());
+
+        let (process, events) = match output::run(&cmd, &self.model.data.config) {
+            Ok(started) => started,
+            Err(e)      => {
+                self.output_page.set_status(&e);
+                return false;
+            },
+        };
+
+        self.output_process = Some(process);
+
+        let current = self.output_run.clone();
+        let relm    = self.relm.clone();
+
+        gtk::timeout_add(50, move || {
+            // A newer run took over the page
+            if current.get() != run { return gtk::Continue(false) }
+
+            loop {
+                match events.try_recv() {
+                    Ok(event) => {
+                        let last = event.is_final();
+                        relm.stream().emit(Msg::Output(run, event));
+                        if last { return gtk::Continue(false) }
+                    },
+                    Err(mpsc::TryRecvError::Empty)        => return gtk::Continue(true),
+                    Err(mpsc::TryRecvError::Disconnected) => return gtk::Continue(false),
+                }
+            }
+        });
 
-    for line in list.iter() {
-        writer.write(line.as_bytes())?;
-        writer.write("\n".as_bytes())?;
+        true
     }
 
-    Ok(())
-}
+    /// Stop the command of the Output page if it's still running
+    fn stop_output(&mut self) {
+        if let Some(process) = self.output_process.take() {
+            process.kill();
+        }
+    }
 
-/// Read a list of commands from a file
-fn read_file_list(store: FileStore) -> Result<Vec<String>, Box<std::error::Error>> {
-    let mut path = PathBuf::from(std::env::var("HOME")?);
+    fn output_event(&mut self, run: u32, event: output::Event) {
+        if run != self.output_run.get() { return }
 
-    match store {
-        FileStore::Bookmarks => path.push(".config/influence/bookmarks.txt"),
-        FileStore::History   => path.push(".config/influence/history.txt"),
+        match event {
+            output::Event::Stdout(text)       => self.output_page.append(&text, false),
+            output::Event::Stderr(text)       => self.output_page.append(&text, true),
+            output::Event::Exited(Some(0))    => self.output_page.set_status("Done"),
+            output::Event::Exited(Some(code)) => self.output_page.set_status(&format!("Exited with status {}", code)),
+            output::Event::Exited(None)       => self.output_page.set_status("Killed by a signal"),
+            output::Event::Failed(message)    => self.output_page.set_status(&message),
+        }
     }
 
-    let mut string = String::new();
-    let mut file = File::open(path)?;
-    let _ = file.read_to_string(&mut string)?;
-    let bookmarks = string
-        .lines()
-        .map(|l| l.to_string())
-        .collect::<Vec<String>>();
+    fn copy_output(&self) {
+        let clipboard = gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD);
+        clipboard.set_text(&self.output_page.text());
+        clipboard.store();
+    }
+
+    /// Show an error below the entry, until the entry changes
+    fn show_error(&self, message: &str) {
+        eprintln!("{}", message);
+        self.status_label.set_text(message);
+        self.status_label.show();
+    }
+
+    fn quit(&mut self) {
+        match self.model.mode {
+            Mode::Launcher if self.model.resident => self.hide(),
+            Mode::Launcher                        => {
+                self.stop_output();
+                gtk::main_quit();
+            },
+            Mode::Dmenu { .. }                    => process::exit(1), // nothing was chosen
+        }
+    }
+
+    /// Hide the window and reset it for the next time it's shown
+    fn hide(&mut self) {
+        self.stop_output();
+        self.window.hide();
+        self.status_label.hide();
+        self.model.focus_results_page = true;
+        self.command_entry.set_text("");
+        self.notebook.set_property_page(0);
+    }
+
+    fn show(&self) {
+        placement::place(&self.window, &self.model.data.config.window);
+        self.window.present();
+        self.command_entry.grab_focus();
+    }
+
+    fn handle_request(&mut self, request: Request) {
+        match request {
+            Request::Show => self.show(),
+            Request::Toggle => {
+                if self.window.get_visible() {
+                    self.hide();
+                } else {
+                    self.show();
+                }
+            },
+            Request::Page(name) => {
+                self.show();
+                self.select_page(Page::Name(name));
+            },
+            Request::Exit => gtk::main_quit(),
+        }
+    }
 
-    Ok(bookmarks)
+    fn complete_entry(&self) {
+        if let Some(activation) = self.get_selected_activation() {
+            let text = activation.text();
+            self.command_entry.set_text(text);
+            self.command_entry.set_position(text.chars().count() as i32);
+        }
+    }
 }
 
-/// Get all executables in the user's PATH
-fn get_path_commands() -> Vec<String> {
-    env::var("PATH")
-        .ok().unwrap_or_else(String::new)
-        .split(":")
-        .map(fs::read_dir)
+/// Load the configured theme and style.css, returning any errors in them
+fn load_style(styles: &theme::Theme, config: &Config) -> Vec<String> {
+    let style = match store::store_path(FileStore::Style) {
+        Ok(path) => path,
+        Err(e)   => return vec![format!("unable to load style.css: {}", e)],
+    };
+
+    let theme = config.theme.as_ref().and_then(|name| store::store_path(FileStore::Theme(name.clone())).ok());
+    styles.reload(theme.as_ref().map(|p| p.as_path()), &style)
+}
+
+/// Row for an item on the Results page or a provider page, with the
+/// characters matching the query highlighted
+fn item_row(item: &Item, query: &str) -> gtk::Box {
+    let icon      = item.icon.as_ref().map(|s| s.as_str());
+    let subtitle  = item.subtitle.as_ref().map(|s| s.as_str());
+    let highlight = fuzzy::positions(query, &item.label).unwrap_or_default();
+    gui::item_row(icon, &item.label, subtitle, &highlight)
+}
+
+/// Read dmenu items from stdin, one per line
+fn read_stdin_items() -> Vec<String> {
+    let stdin = io::stdin();
+    let items = stdin.lock()
+        .lines()
         .flat_map(Result::ok)
-        .flat_map(Itertools::flatten)
-        .filter(|file|
-            file.metadata().ok()
-                .map_or(false, |meta| meta.permissions().mode() & 0o111 > 0))
-        .flat_map(|file| file.file_name().into_string())
-        .unique()
-        .sorted()
+        .collect();
+
+    items
 }
 
 fn main() {
-    Win::run(()).unwrap();
+    let args = cli::parse(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("influence: {}\n\n{}", e, cli::USAGE);
+        process::exit(2);
+    });
+
+    if args.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    // Set before anything else runs, so the daemon and the scripts it starts
+    // use the same directory. Made absolute, so clients started from another
+    // directory find the same daemon.
+    if let Some(ref dir) = args.config {
+        let dir = env::current_dir().map(|cwd| cwd.join(dir)).unwrap_or_else(|_| PathBuf::from(dir));
+        env::set_var("INFLUENCE_CONFIG", dir);
+    }
+
+    match store::migrate() {
+        Ok(moved) => for (old, new) in moved {
+            eprintln!("influence: moved {} to {}", old.display(), new.display());
+        },
+        Err(e) => eprintln!("influence: unable to move files from ~/.config/influence: {}", e),
+    }
+
+    if let Some(ref pattern) = args.purge {
+        let mut removed = 0;
+
+        if let Err(e) = store::update_history(|entries| removed = history::purge(entries, pattern)) {
+            eprintln!("influence: unable to purge history: {}", e);
+            process::exit(1);
+        }
+
+        println!("Removed {} commands from the history", removed);
+        return;
+    }
+
+    // Shown in the window, which is all a launcher started from a hotkey has
+    let mut errors = vec![];
+
+    let config = store::store_path(FileStore::Config)
+        .and_then(|path| Config::load(&path))
+        .unwrap_or_else(|e| {
+            errors.push(format!("unable to load config.toml, using defaults: {}", e));
+            Config::default()
+        });
+
+    if args.dmenu {
+        let mode = Mode::Dmenu { prompt: args.prompt };
+        let data = influence_core::Model::dmenu(config, read_stdin_items());
+
+        Win::run(Options { mode, data, requests: None, page: args.page, errors }).unwrap();
+        return;
+    }
+
+    if args.daemon {
+        let requests = daemon::listen().unwrap_or_else(|e| {
+            eprintln!("influence: unable to start daemon: {}", e);
+            process::exit(1);
+        });
+
+        let data = influence_core::Model::load(config);
+
+        Win::run(Options { mode: Mode::Launcher, data, requests: Some(requests), page: args.page, errors }).unwrap();
+        daemon::cleanup();
+        return;
+    }
+
+    // Let a running daemon show its window instead of starting a new one
+    let request = match args.page {
+        _ if args.exit   => Request::Exit,
+        _ if args.toggle => Request::Toggle,
+        Some(ref page)   => Request::Page(page.clone()),
+        None             => Request::Show,
+    };
+
+    match daemon::send(&request) {
+        Ok(()) => return,
+        Err(_) if args.exit => {
+            eprintln!("influence: no daemon is running");
+            process::exit(1);
+        },
+        Err(_) => (),
+    }
+
+    let data = influence_core::Model::load(config);
+
+    Win::run(Options { mode: Mode::Launcher, data, requests: None, page: args.page, errors }).unwrap();
 }
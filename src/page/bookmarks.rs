@@ -1,7 +1,10 @@
 use gtk;
 use gtk::{MovementStep, Orientation};
 use gtk::prelude::*;
-use {Context, FocusTarget, Msg, RunOptions};
+use influence_core::bookmark::{Bookmark, Entry};
+use gui;
+use keys::{Action, Scope};
+use {BookmarkEdit, CommandSource, Context, FocusTarget, Msg, RunOptions};
 
 pub fn init_page(context: &Context) -> gtk::ListBox {
     let listbox = gtk::ListBox::new();
@@ -9,50 +12,51 @@ pub fn init_page(context: &Context) -> gtk::ListBox {
     listbox.set_vexpand(true);
     listbox.set_valign(gtk::Align::Fill);
 
-    for bookmark in &context.model.bookmarks {
-        if bookmark == "" {
-            let row = gtk::ListBoxRow::new();
-            let sep = gtk::Separator::new(Orientation::Horizontal);
-            row.set_sensitive(false);
-            row.set_can_focus(false);
-            row.add(&sep);
-            listbox.add(&row);
-        } else {
-            let label = gtk::Label::new(Some(bookmark.as_str()));
-            label.set_halign(gtk::Align::Start);
-            listbox.add(&label);
-        }
-    }
+    fill(&listbox, &context.model.data.bookmarks);
 
-    if let Some(first_row) = listbox.get_row_at_index(0) {
+    let first_row = listbox.get_children().into_iter()
+        .find(|row| row.get_can_focus());
+
+    if let Some(first_row) = first_row {
         listbox.set_focus_child(&first_row);
     }
 
+    // Rows don't always show the command itself, so let Win look it up
     connect!(
         context.relm,
         listbox,
-        connect_row_activated(_, row),
-        {
-            let label = row.get_child().unwrap().downcast::<gtk::Label>().unwrap();
-            let cmd = label.get_text().map(|s| s.to_string()).unwrap_or_default();
-            Some(Msg::RunCommand(cmd, RunOptions {
-                quit: true,
-                record: true,
-            }))
-        }
+        connect_row_activated(_, _),
+        Some(Msg::RunCommandFromSource(CommandSource::ListSelection(false), RunOptions {
+            quit: true,
+            record: true,
+            terminal: false,
+        }))
     );
 
+    let keys = context.model.keys.clone();
     connect!(
         context.relm,
         listbox,
-        connect_key_press_event(listbox, key),
+        connect_key_press_event(listbox, ev),
         return {
-            use gdk::enums::key;
-            match key.get_keyval() {
-                key::Tab => (Some(Msg::ShiftFocus(FocusTarget::Entry)), Inhibit(true)),
+            use BookmarkEdit::*;
 
-                k @ key::Up |
-                k @ key::Down => listbox_skip_separators!(listbox, k),
+            let edit = |f: fn(i32) -> BookmarkEdit| (
+                listbox.get_selected_row().map(|row| Msg::EditBookmarks(f(row.get_index()))),
+                Inhibit(true)
+            );
+
+            match keys.action(ev, &[Scope::List, Scope::Bookmarks]) {
+                Some(Action::FocusEntry) => (Some(Msg::ShiftFocus(FocusTarget::Entry)), Inhibit(true)),
+
+                Some(Action::DeleteBookmark)   => edit(Remove),
+                Some(Action::DeleteSeparator)  => edit(RemoveSeparator),
+                Some(Action::InsertSeparator)  => edit(InsertSeparator),
+                Some(Action::MoveBookmarkUp)   => edit(|i| Move(i, -1)),
+                Some(Action::MoveBookmarkDown) => edit(|i| Move(i,  1)),
+
+                Some(Action::SelectPrev) => listbox_skip_separators!(listbox, -1),
+                Some(Action::SelectNext) => listbox_skip_separators!(listbox,  1),
 
                 _ => (None, Inhibit(false)),
             }
@@ -61,3 +65,37 @@ pub fn init_page(context: &Context) -> gtk::ListBox {
 
     listbox
 }
+
+/// Replace the rows of the listbox with the given bookmarks
+pub fn fill(listbox: &gtk::ListBox, bookmarks: &[Entry]) {
+    for row in listbox.get_children() {
+        listbox.remove(&row);
+    }
+
+    for entry in bookmarks {
+        match *entry {
+            Entry::Separator => {
+                let row = gtk::ListBoxRow::new();
+                let sep = gtk::Separator::new(Orientation::Horizontal);
+                row.set_sensitive(false);
+                row.set_can_focus(false);
+                row.add(&sep);
+                listbox.add(&row);
+            },
+            Entry::Header(ref title) => {
+                listbox.add(&gui::header_row(title));
+            },
+            Entry::Bookmark(ref bookmark) => {
+                listbox.add(&bookmark_row(bookmark));
+            },
+        }
+    }
+
+    listbox.show_all();
+}
+
+/// Icon, name and dimmed command of a bookmark, or just the command if it has no name
+fn bookmark_row(bookmark: &Bookmark) -> gtk::Box {
+    let subtitle = bookmark.name.as_ref().map(|_| bookmark.command.as_str());
+    gui::item_row(bookmark.icon.as_ref().map(|s| s.as_str()), bookmark.title(), subtitle, &[])
+}
@@ -1,7 +1,9 @@
 use gtk;
 use gtk::MovementStep;
 use gtk::prelude::*;
-use {Context, FocusTarget, Msg, RunOptions};
+use influence_core::history::HistoryEntry;
+use keys::{Action, Scope};
+use {BookmarkEdit, CommandSource, Context, FocusTarget, Msg, RunOptions};
 
 pub fn init_page(context: &Context) -> gtk::ListBox {
     let listbox = gtk::ListBox::new();
@@ -9,11 +11,7 @@ pub fn init_page(context: &Context) -> gtk::ListBox {
     listbox.set_vexpand(true);
     listbox.set_valign(gtk::Align::Fill);
 
-    for entry in &context.model.history {
-        let label = gtk::Label::new(Some(entry.as_str()));
-        label.set_halign(gtk::Align::Start);
-        listbox.add(&label);
-    }
+    fill(&listbox, &context.model.data.history);
 
     if let Some(first_row) = listbox.get_row_at_index(0) {
         listbox.set_focus_child(&first_row);
@@ -22,43 +20,53 @@ pub fn init_page(context: &Context) -> gtk::ListBox {
     connect!(
         context.relm,
         listbox,
-        connect_row_activated(_, row),
-        {
-            let label = row.get_child().unwrap().downcast::<gtk::Label>().unwrap();
-            let cmd = label.get_text().map(|s| s.to_string()).unwrap_or_default();
-            Some(Msg::RunCommand(cmd, RunOptions {
-                quit: true,
-                record: true,
-            }))
-        }
+        connect_row_activated(_, _),
+        Some(Msg::RunCommandFromSource(CommandSource::ListSelection(false), RunOptions {
+            quit: true,
+            record: true,
+            terminal: false,
+        }))
     );
 
+    let keys = context.model.keys.clone();
     connect!(
         context.relm,
         listbox,
         connect_key_press_event(listbox, ev),
-        return {
-            use gdk::enums::key;
-            use gdk::ModifierType;
+        return match keys.action(ev, &[Scope::List, Scope::History]) {
+            Some(Action::FocusEntry) => (Some(Msg::ShiftFocus(FocusTarget::Entry)), Inhibit(true)),
 
-            let state      = ev.get_state();
-            let shift_held = state.contains(ModifierType::SHIFT_MASK);
+            Some(Action::DeleteHistory) => (
+                listbox.get_selected_row().map(|row| Msg::RemoveHistoryEntry(row.get_index())),
+                Inhibit(true)
+            ),
 
-            match ev.get_keyval() {
-                key::Tab => (Some(Msg::ShiftFocus(FocusTarget::Entry)), Inhibit(true)),
+            Some(Action::Bookmark) => (
+                Some(Msg::EditBookmarks(BookmarkEdit::Add(CommandSource::ListSelection(false)))),
+                Inhibit(true)
+            ),
 
-                key::Delete if shift_held => (
-                    listbox.get_selected_row().map(|row| Msg::RemoveHistoryEntry(row.get_index())),
-                    Inhibit(true)
-                ),
+            Some(Action::SelectPrev) => listbox_skip_separators!(listbox, -1),
+            Some(Action::SelectNext) => listbox_skip_separators!(listbox,  1),
 
-                k @ key::Up |
-                k @ key::Down => listbox_skip_separators!(listbox, k),
-
-                _ => (None, Inhibit(false)),
-            }
+            _ => (None, Inhibit(false)),
         }
     );
 
     listbox
 }
+
+/// Replace the rows of the listbox with the given history
+pub fn fill(listbox: &gtk::ListBox, history: &[HistoryEntry]) {
+    for row in listbox.get_children() {
+        listbox.remove(&row);
+    }
+
+    for entry in history {
+        let label = gtk::Label::new(Some(entry.command.as_str()));
+        label.set_halign(gtk::Align::Start);
+        listbox.add(&label);
+    }
+
+    listbox.show_all();
+}
@@ -0,0 +1,49 @@
+use gtk;
+use gtk::MovementStep;
+use gtk::prelude::*;
+use keys::{Action, Scope};
+use {CommandSource, Context, FocusTarget, Msg, RunOptions};
+
+/// Page listing the items read from stdin in dmenu mode
+pub fn init_page(context: &Context) -> gtk::ListBox {
+    let listbox = gtk::ListBox::new();
+    listbox.set_hexpand(true);
+    listbox.set_vexpand(true);
+    listbox.set_valign(gtk::Align::Fill);
+
+    for item in &context.model.data.items {
+        let label = gtk::Label::new(Some(item.as_str()));
+        label.set_halign(gtk::Align::Start);
+        listbox.add(&label);
+    }
+
+    if let Some(first_row) = listbox.get_row_at_index(0) {
+        listbox.set_focus_child(&first_row);
+    }
+
+    connect!(
+        context.relm,
+        listbox,
+        connect_row_activated(_, _),
+        Some(Msg::RunCommandFromSource(CommandSource::ListSelection(false), RunOptions {
+            quit: true,
+            record: true,
+            terminal: false,
+        }))
+    );
+
+    let keys = context.model.keys.clone();
+    connect!(
+        context.relm,
+        listbox,
+        connect_key_press_event(listbox, ev),
+        return match keys.action(ev, &[Scope::List]) {
+            Some(Action::FocusEntry) => (Some(Msg::ShiftFocus(FocusTarget::Entry)), Inhibit(true)),
+            Some(Action::SelectPrev) => listbox_skip_separators!(listbox, -1),
+            Some(Action::SelectNext) => listbox_skip_separators!(listbox,  1),
+            _                        => (None, Inhibit(false)),
+        }
+    );
+
+    listbox
+}
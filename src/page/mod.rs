@@ -1,3 +1,6 @@
+pub mod applications;
 pub mod bookmarks;
 pub mod history;
+pub mod items;
+pub mod output;
 pub mod results;
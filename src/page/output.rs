@@ -0,0 +1,106 @@
+use gtk;
+use gtk::Orientation;
+use gui;
+use gtk::prelude::*;
+use keys::{Action, Scope};
+use {Context, FocusTarget, Msg};
+
+/// The output of the last command run with ctrl+O
+pub struct OutputPage {
+    pub container: gtk::Box,
+    pub text_view: gtk::TextView,
+    buffer:        gtk::TextBuffer,
+    stderr:        gtk::TextTag,
+    status:        gtk::Label,
+}
+
+pub fn init_page(context: &Context) -> OutputPage {
+    let tags   = gtk::TextTagTable::new();
+    let stderr = gtk::TextTag::new(Some("stderr"));
+    tags.add(&stderr);
+
+    let buffer    = gtk::TextBuffer::new(Some(&tags));
+    let text_view = gtk::TextView::new_with_buffer(&buffer);
+    text_view.set_editable(false);
+    text_view.set_cursor_visible(false);
+    text_view.set_monospace(true);
+    text_view.set_left_margin((context.res_scale)(5));
+
+    // Keeps the view scrolled to the bottom while output comes in
+    buffer.create_mark(Some("end"), &buffer.get_end_iter(), false);
+
+    let scroller = gtk::ScrolledWindow::new(None, None);
+    scroller.set_vexpand(true);
+    scroller.add(&text_view);
+
+    let status = gtk::Label::new(None);
+    status.set_halign(gtk::Align::Start);
+    status.set_hexpand(true);
+    status.set_line_wrap(true);
+
+    let copy_button  = gtk::Button::new_with_label("Copy");
+    let rerun_button = gtk::Button::new_with_label("Rerun");
+
+    let bar = gtk::Box::new(Orientation::Horizontal, (context.res_scale)(5));
+    bar.add(&status);
+    bar.add(&copy_button);
+    bar.add(&rerun_button);
+
+    let container = gtk::Box::new(Orientation::Vertical, (context.res_scale)(5));
+    container.add(&scroller);
+    container.add(&bar);
+
+    connect!(context.relm, copy_button,  connect_clicked(_), Msg::CopyOutput);
+    connect!(context.relm, rerun_button, connect_clicked(_), Msg::RerunOutput);
+
+    let keys = context.model.keys.clone();
+    connect!(
+        context.relm,
+        text_view,
+        connect_key_press_event(_, ev),
+        return match keys.action(ev, &[Scope::Output]) {
+            Some(Action::FocusEntry)  => (Some(Msg::ShiftFocus(FocusTarget::Entry)), Inhibit(true)),
+            Some(Action::RerunOutput) => (Some(Msg::RerunOutput),                   Inhibit(true)),
+            _                         => (None,                                     Inhibit(false)),
+        }
+    );
+
+    OutputPage { container, text_view, buffer, stderr, status }
+}
+
+impl OutputPage {
+    /// Empty the page for a new run of the command
+    pub fn clear(&self, cmd: &str) {
+        self.buffer.set_text("");
+        self.status.set_text(&format!("Running {}", cmd));
+
+        // Looked up on every run, so a reloaded style.css applies to the next
+        let color = gui::lookup_color(&self.text_view, "error_color");
+        self.stderr.set_property_foreground(color.as_ref().map(|c| c.as_str()));
+    }
+
+    /// Add output at the end, in the error style if it came from stderr
+    pub fn append(&self, text: &str, stderr: bool) {
+        let start = self.buffer.get_end_iter().get_offset();
+        self.buffer.insert(&mut self.buffer.get_end_iter(), text);
+
+        if stderr {
+            let start = self.buffer.get_iter_at_offset(start);
+            self.buffer.apply_tag_by_name("stderr", &start, &self.buffer.get_end_iter());
+        }
+
+        if let Some(end) = self.buffer.get_mark("end") {
+            self.text_view.scroll_mark_onscreen(&end);
+        }
+    }
+
+    pub fn set_status(&self, status: &str) {
+        self.status.set_text(status);
+    }
+
+    /// Everything the command printed so far
+    pub fn text(&self) -> String {
+        let (start, end) = self.buffer.get_bounds();
+        self.buffer.get_text(&start, &end, false).unwrap_or_default()
+    }
+}
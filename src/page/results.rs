@@ -1,7 +1,8 @@
 use gtk;
 use gtk::MovementStep;
 use gtk::prelude::*;
-use {Context, FocusTarget, Msg, RunOptions};
+use keys::{Action, Scope};
+use {CommandSource, Context, FocusTarget, Msg, RunOptions};
 
 pub fn init_page(context: &Context) -> gtk::ListBox {
     let listbox = gtk::ListBox::new();
@@ -9,34 +10,28 @@ pub fn init_page(context: &Context) -> gtk::ListBox {
     listbox.set_vexpand(true);
     listbox.set_valign(gtk::Align::Fill);
 
+    // Rows don't always show the command itself, so let Win look it up
     connect!(
         context.relm,
         listbox,
-        connect_row_activated(_, row),
-        {
-            let label = row.get_child().unwrap().downcast::<gtk::Label>().unwrap();
-            let cmd = label.get_text().map(|s| s.to_string()).unwrap_or_default();
-            Some(Msg::RunCommand(cmd, RunOptions {
-                quit: true,
-                record: true,
-            }))
-        }
+        connect_row_activated(_, _),
+        Some(Msg::RunCommandFromSource(CommandSource::ListSelection(false), RunOptions {
+            quit: true,
+            record: true,
+            terminal: false,
+        }))
     );
 
+    let keys = context.model.keys.clone();
     connect!(
         context.relm,
         listbox,
-        connect_key_press_event(listbox, key),
-        return {
-            use gdk::enums::key;
-            match key.get_keyval() {
-                key::Tab => (Some(Msg::ShiftFocus(FocusTarget::Entry)), Inhibit(true)),
-
-                k @ key::Up |
-                k @ key::Down => listbox_skip_separators!(listbox, k),
-
-                _ => (None, Inhibit(false)),
-            }
+        connect_key_press_event(listbox, ev),
+        return match keys.action(ev, &[Scope::List]) {
+            Some(Action::FocusEntry) => (Some(Msg::ShiftFocus(FocusTarget::Entry)), Inhibit(true)),
+            Some(Action::SelectPrev) => listbox_skip_separators!(listbox, -1),
+            Some(Action::SelectNext) => listbox_skip_separators!(listbox,  1),
+            _                        => (None, Inhibit(false)),
         }
     );
 
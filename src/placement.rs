@@ -0,0 +1,95 @@
+//! Choosing the monitor to show the window on and where to put it there
+
+use influence_core::config::{Anchor, Monitor, MonitorChoice, WindowConfig};
+use gdk;
+use gdk::prelude::*;
+use gtk;
+use gtk::prelude::*;
+
+/// Move and resize the window according to the config
+///
+/// Called every time the window is shown, so that it follows the pointer or
+/// the focused window in daemon mode.
+pub fn place(window: &gtk::Window, config: &WindowConfig) {
+    let screen = match window.get_screen() {
+        Some(screen) => screen,
+        None         => return,
+    };
+
+    let monitor    = screen.get_monitor_geometry(monitor(&screen, config.monitor));
+    let resolution = screen.get_property_resolution() / 96.0;
+    let res_scale  = |i: i32| ((i as f64) * resolution) as i32;
+
+    let (x, y, width) = position(
+        config.anchor,
+        &monitor,
+        res_scale(config.width),
+        res_scale(config.height),
+        res_scale(config.padding),
+        (res_scale(config.offset_x), res_scale(config.offset_y)),
+    );
+
+    window.move_(x, y);
+    window.resize(width, res_scale(config.height));
+}
+
+/// The index of the configured monitor, the primary one if it can't be found
+fn monitor(screen: &gdk::Screen, monitor: Monitor) -> i32 {
+    let primary = screen.get_primary_monitor();
+
+    match monitor {
+        Monitor::Index(index) if index < screen.get_n_monitors() => index,
+        Monitor::Index(index) => {
+            eprintln!("Monitor {} doesn't exist, using the primary monitor", index);
+            primary
+        },
+        Monitor::Named(MonitorChoice::Primary) => primary,
+        Monitor::Named(MonitorChoice::Pointer) => {
+            screen.get_display()
+                .get_device_manager()
+                .map(|manager| {
+                    let (_, x, y) = manager.get_client_pointer().get_position();
+                    screen.get_monitor_at_point(x, y)
+                })
+                .unwrap_or(primary)
+        },
+        Monitor::Named(MonitorChoice::Focused) => {
+            // Uses _NET_ACTIVE_WINDOW, so this needs a window manager that sets it
+            screen.get_active_window()
+                .map(|active| screen.get_monitor_at_window(&active))
+                .unwrap_or(primary)
+        },
+    }
+}
+
+/// Top left corner and width of a window of the given size, anchored to the
+/// monitor with padding from its edges
+fn position(anchor: Anchor, monitor: &gdk::Rectangle, width: i32, height: i32, padding: i32, offset: (i32, i32)) -> (i32, i32, i32) {
+    use influence_core::config::Anchor::*;
+
+    let width = match anchor {
+        TopBar => monitor.width - 2 * padding,
+        _      => width,
+    };
+
+    let left    = monitor.x + padding;
+    let hcenter = monitor.x + (monitor.width - width) / 2;
+    let right   = monitor.x + monitor.width - padding - width;
+    let top     = monitor.y + padding;
+    let vcenter = monitor.y + (monitor.height - height) / 2;
+    let bottom  = monitor.y + monitor.height - padding - height;
+
+    let x = match anchor {
+        TopLeft | Left | BottomLeft | TopBar => left,
+        Top | Center | Bottom                => hcenter,
+        TopRight | Right | BottomRight       => right,
+    };
+
+    let y = match anchor {
+        TopLeft | Top | TopRight | TopBar => top,
+        Left | Center | Right             => vcenter,
+        BottomLeft | Bottom | BottomRight => bottom,
+    };
+
+    (x + offset.0, y + offset.1, width)
+}
@@ -0,0 +1,96 @@
+//! Styling the window with the built-in CSS, a named theme and the user's style.css
+//!
+//! Each layer is added at a higher priority than the one before it, so a theme
+//! can override the built-in style and style.css can override both.
+
+use gdk;
+use gtk;
+use gtk::prelude::*;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+pub struct Theme {
+    builtin: gtk::CssProvider,
+    theme:   gtk::CssProvider,
+    user:    gtk::CssProvider,
+
+    /// Line and message of each error in the CSS being loaded
+    errors:  Rc<RefCell<Vec<(u32, String)>>>,
+}
+
+impl Theme {
+    /// Add the style layers to the screen, with only the built-in CSS loaded
+    pub fn new(screen: &gdk::Screen) -> Theme {
+        let theme = Theme {
+            builtin: gtk::CssProvider::new(),
+            theme:   gtk::CssProvider::new(),
+            user:    gtk::CssProvider::new(),
+            errors:  Rc::new(RefCell::new(vec![])),
+        };
+
+        let priority = gtk::STYLE_PROVIDER_PRIORITY_APPLICATION;
+
+        for (i, provider) in [&theme.builtin, &theme.theme, &theme.user].iter().enumerate() {
+            let errors = theme.errors.clone();
+            provider.connect_parsing_error(move |_, section, error| {
+                errors.borrow_mut().push((section.get_start_line() + 1, error.to_string()));
+            });
+
+            gtk::StyleContext::add_provider_for_screen(screen, *provider, priority + i as u32);
+        }
+
+        for error in theme.load(&theme.builtin, "main.css", include_str!("main.css")) {
+            eprintln!("{}", error);
+        }
+
+        theme
+    }
+
+    /// Load the named theme and style.css, replacing what was loaded before
+    ///
+    /// Returns the errors in either file, which are skipped over rather than
+    /// stopping the rest of the file from being used.
+    pub fn reload(&self, theme: Option<&Path>, style: &Path) -> Vec<String> {
+        let mut errors = vec![];
+
+        match theme {
+            Some(path) => match read_css(path) {
+                Ok(css) => errors.extend(self.load(&self.theme, &path.to_string_lossy(), &css)),
+                Err(e)  => {
+                    errors.push(format!("unable to read theme {}: {}", path.display(), e));
+                    self.load(&self.theme, "", "");
+                },
+            },
+            None => { self.load(&self.theme, "", ""); },
+        }
+
+        match read_css(style) {
+            Ok(css) => errors.extend(self.load(&self.user, &style.to_string_lossy(), &css)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => { self.load(&self.user, "", ""); },
+            Err(e)  => errors.push(format!("unable to read {}: {}", style.display(), e)),
+        }
+
+        errors
+    }
+
+    fn load(&self, provider: &gtk::CssProvider, name: &str, css: &str) -> Vec<String> {
+        // The result only repeats the first error, which the parsing-error
+        // signal has already collected with its line number
+        let _ = provider.load_from_data(css.as_bytes());
+
+        self.errors.borrow_mut()
+            .drain(..)
+            .map(|(line, message)| format!("{} line {}: {}", name, line, message))
+            .collect()
+    }
+}
+
+fn read_css(path: &Path) -> io::Result<String> {
+    let mut css = String::new();
+    File::open(path)?.read_to_string(&mut css)?;
+    Ok(css)
+}
@@ -0,0 +1,93 @@
+//! Watching bookmarks, history, styles and `$PATH` for changes with inotify
+
+use inotify::{Inotify, WatchMask};
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+
+/// Something that changed on disk and needs to be reloaded
+#[derive(Clone, Copy, PartialEq)]
+pub enum Change {
+    Bookmarks,
+    History,
+    Programs,
+    Style,
+}
+
+/// Start watching in the background
+///
+/// Changes are sent through the returned channel, to be picked up by the GTK
+/// main loop. The config and state directories are created if they don't
+/// exist yet, so changes made later are seen. Directories that still can't be
+/// watched are skipped.
+pub fn watch(config_dir: &Path, state_dir: &Path) -> io::Result<mpsc::Receiver<Change>> {
+    let mut inotify = Inotify::init()?;
+
+    for dir in &[config_dir, state_dir] {
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("unable to create {}: {}", dir.display(), e);
+        }
+    }
+
+    // Watch the directory rather than the files, since editors tend to save
+    // by writing a new file and renaming it over the old one
+    let config_watch = inotify.add_watch(config_dir,
+        WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE | WatchMask::DELETE)
+        .map_err(|e| eprintln!("unable to watch {}: {}", config_dir.display(), e))
+        .ok();
+
+    let state_watch = inotify.add_watch(state_dir,
+        WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE | WatchMask::DELETE)
+        .map_err(|e| eprintln!("unable to watch {}: {}", state_dir.display(), e))
+        .ok();
+
+    let themes_watch = inotify.add_watch(&config_dir.join("themes"),
+        WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE | WatchMask::DELETE)
+        .ok();
+
+    let path_watches = env::var("PATH")
+        .unwrap_or_default()
+        .split(':')
+        .flat_map(|dir| inotify.add_watch(dir,
+            WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVED_FROM | WatchMask::MOVED_TO | WatchMask::ATTRIB))
+        .collect::<HashSet<_>>();
+
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut buffer = [0; 4096];
+
+        loop {
+            let events = match inotify.read_events_blocking(&mut buffer) {
+                Ok(events) => events,
+                Err(e)     => return eprintln!("unable to read inotify events: {}", e),
+            };
+
+            for event in events {
+                // Both are the same directory with $INFLUENCE_CONFIG
+                let change = if Some(&event.wd) == config_watch.as_ref() || Some(&event.wd) == state_watch.as_ref() {
+                    match event.name.and_then(|name| name.to_str()) {
+                        Some("bookmarks.txt") => Change::Bookmarks,
+                        Some("history.txt")   => Change::History,
+                        Some("style.css")     => Change::Style,
+                        _                     => continue,
+                    }
+                } else if Some(&event.wd) == themes_watch.as_ref() {
+                    Change::Style
+                } else if path_watches.contains(&event.wd) {
+                    Change::Programs
+                } else {
+                    continue;
+                };
+
+                if sender.send(change).is_err() { return }
+            }
+        }
+    });
+
+    Ok(receiver)
+}